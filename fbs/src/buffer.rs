@@ -61,7 +61,7 @@ impl Buffer {
     pub unsafe fn new(len: usize) -> Self {
         let ptr = RawBuffer::alloc(len);
         Self {
-            raw: Box::from_raw(ptr.as_ptr()).into(),
+            raw: Box::from_raw(ptr.as_ptr()),
         }
     }
 
@@ -105,6 +105,26 @@ impl Buffer {
         Ok(&*(cur as *const T))
     }
 
+    /// Get nth optional scalar member of the table
+    ///
+    /// Returns `None` if the member is absent in the buffer, which means `null` for
+    /// optional scalars declared with `= null` rather than falling back to the default.
+    ///
+    /// Safety
+    /// -------
+    /// The value should be broken if incorrect type `T` is specified
+    pub unsafe fn get_optional<T: Copy>(&self, n: usize) -> Option<T> {
+        let (vtable, table) = self.get_tables();
+        // vtable may be shorter than the number of members if trailing ones are absent
+        let offset = *vtable.offsets.get(n)?;
+        if offset == 0 {
+            return None;
+        }
+        let cur = table as *const Table as *const u8;
+        let cur = cur.offset(offset as isize);
+        Some(ptr::read_unaligned(cur as *const T))
+    }
+
     /// Get nth member string on the table
     pub fn get_str(&self, n: usize) -> Result<&str> {
         let cstr = unsafe {
@@ -149,7 +169,7 @@ impl Buffer {
             (*table_fat_ptr).vtable_offset
         };
 
-        let vtable_ptr = (table_ptr as *const u8).offset(-vtable_offset as isize);
+        let vtable_ptr = table_ptr.offset(-vtable_offset as isize);
 
         // Read vtable header
         let (vtable_length, table_length) = {
@@ -182,19 +202,17 @@ impl Buffer {
 mod tests {
     use super::*;
 
-    const TEST_HEADER: &'static [u8] = &[
-        0x00, 0x01, 0x00, 0x00, 'N' as u8, 'O' as u8, 'O' as u8, 'B' as u8, '\0' as u8,
-    ];
+    const TEST_HEADER: &[u8] = &[0x00, 0x01, 0x00, 0x00, b'N', b'O', b'O', b'B', b'\0'];
 
     #[test]
     fn test_new() {
-        let buf = Buffer::copy_from_slice(&TEST_HEADER);
+        let buf = Buffer::copy_from_slice(TEST_HEADER);
         assert_eq!(buf.raw.file_identifier.len(), 5);
     }
 
     #[test]
     fn test_identifier() {
-        let buf = Buffer::copy_from_slice(&TEST_HEADER);
+        let buf = Buffer::copy_from_slice(TEST_HEADER);
         assert_eq!(buf.file_identifier().unwrap(), "NOOB");
     }
 
@@ -219,5 +237,12 @@ mod tests {
         }
         // FooBar.say
         assert_eq!(fb.get_str(2).unwrap(), "hello");
+
+        unsafe {
+            assert_eq!(fb.get_optional::<i8>(0), Some(42_i8));
+            // absent members are None
+            assert_eq!(fb.get_optional::<i64>(1), None);
+            assert_eq!(fb.get_optional::<i16>(4), None);
+        }
     }
 }
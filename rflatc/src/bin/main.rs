@@ -1,26 +1,98 @@
 //! FlatBuffers compiler
+//!
+//! ```text
+//! rflatc [-I <include-dir>]... [schema.fbs]
+//! ```
+//!
+//! The schema is read from stdin if no file is specified,
+//! where included files are searched in the current directory and then include directories.
 
-use combine::Parser;
-use rflatc::{parser::fbs, remove_comment, semantics::Buffer};
+use rflatc::{
+    diagnostics::{Diagnostic, Diagnostics},
+    loader, semantics,
+};
 
-use std::io::Read;
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    process::exit,
+};
 
-fn main() {
+/// File name of the schema read from stdin used in spans
+const STDIN: &str = "<stdin>";
+
+/// Print an error to stderr with the source snippet if it has a location, and exit
+///
+/// `stdin` is the input read from stdin, used since it cannot be read again from the span.
+fn report(err: failure::Error, stdin: Option<&str>) -> ! {
+    let render = |diag: &Diagnostic| {
+        let file = diag.span.file.as_ref().map(|f| f.as_path());
+        match stdin {
+            Some(source) if file == Some(Path::new(STDIN)) => diag.render(source),
+            _ => diag.render_file(),
+        }
+    };
+    if let Some(diag) = err.downcast_ref::<Diagnostic>() {
+        eprintln!("{}", render(diag));
+    } else if let Some(diags) = err.downcast_ref::<Diagnostics>() {
+        for diag in &diags.0 {
+            eprintln!("{}\n", render(diag));
+        }
+    } else {
+        eprintln!("error: {}", err);
+    }
+    exit(1)
+}
+
+fn read_stdin() -> String {
     let mut input = String::new();
-    let size = std::io::stdin()
-        .read_to_string(&mut input)
-        .expect("Failed to read input");
-    if size == 0 {
-        panic!("Input is empty");
+    if let Err(e) = std::io::stdin().read_to_string(&mut input) {
+        report(e.into(), None);
     }
+    if input.is_empty() {
+        report(failure::err_msg("Input is empty"), None);
+    }
+    input
+}
 
-    let input = remove_comment(&input);
+fn main() {
+    let mut include_paths = Vec::new();
+    let mut schema = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "-I" {
+            let dir = args
+                .next()
+                .unwrap_or_else(|| report(failure::err_msg("-I requires a directory"), None));
+            include_paths.push(PathBuf::from(dir));
+        } else {
+            schema = Some(PathBuf::from(arg));
+        }
+    }
 
-    let (stmt, res) = fbs().parse(input.as_str()).expect("Failed to parse");
-    assert_eq!(res, "");
+    let stdin = match schema {
+        Some(_) => None,
+        None => Some(read_stdin()),
+    };
+    let stdin = stdin.as_deref();
+    let stmt = match (&schema, stdin) {
+        (Some(path), _) => match loader::load(path, &include_paths) {
+            Ok(sources) => loader::merge(&sources),
+            Err(e) => report(e, None),
+        },
+        (None, input) => {
+            let input = input.unwrap_or_default();
+            match loader::load_str(input, Path::new(STDIN), &include_paths) {
+                Ok(sources) => loader::merge(&sources),
+                Err(e) => report(e, stdin),
+            }
+        }
+    };
 
     println!("{:?}", stmt);
 
-    let buffer = Buffer::new(stmt);
-    println!("{:?}", buffer);
+    match semantics::analyze(&stmt) {
+        Ok(schema) => println!("{:?}", schema),
+        Err(e) => report(e, stdin),
+    }
 }
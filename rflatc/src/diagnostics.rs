@@ -0,0 +1,213 @@
+//! Compiler-style diagnostics
+//!
+//! ```text
+//! example.fbs:18:11: error: Cannot find table: FooBaz
+//!    |
+//! 18 | root_type FooBaz;
+//!    |           ^^^^^^
+//! ```
+
+use crate::parser::Span;
+use combine::stream::easy;
+use std::{fmt, fs};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Error => write!(f, "error"),
+            Level::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Error or warning with the location in schema
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error<M: Into<String>>(span: &Span, message: M) -> Self {
+        Diagnostic {
+            level: Level::Error,
+            span: span.clone(),
+            message: message.into(),
+        }
+    }
+
+    pub fn warning<M: Into<String>>(span: &Span, message: M) -> Self {
+        Diagnostic {
+            level: Level::Warning,
+            span: span.clone(),
+            message: message.into(),
+        }
+    }
+
+    /// Convert an error of `combine::Parser::easy_parse` into a diagnostic
+    pub fn from_parse_error<R: fmt::Display>(err: easy::Errors<char, R, Span>) -> Self {
+        let mut unexpected = None;
+        let mut expected = Vec::new();
+        let mut messages = Vec::new();
+        for e in &err.errors {
+            match e {
+                easy::Error::Unexpected(info) => unexpected = Some(quote(info)),
+                easy::Error::Expected(info) => expected.push(quote(info)),
+                easy::Error::Message(info) => messages.push(info.to_string()),
+                easy::Error::Other(e) => messages.push(e.to_string()),
+            }
+        }
+        let mut message = Vec::new();
+        if let Some(unexpected) = unexpected {
+            message.push(format!("unexpected {}", unexpected));
+        }
+        if !expected.is_empty() {
+            message.push(format!("expected {}", expected.join(", ")));
+        }
+        message.extend(messages);
+        Diagnostic::error(&err.position, message.join("; "))
+    }
+
+    /// Render with the source line and caret underline
+    ///
+    /// `source` is the content of the file where the diagnostic is reported.
+    pub fn render(&self, source: &str) -> String {
+        let header = self.to_string();
+        let line = match source.lines().nth((self.span.line - 1).max(0) as usize) {
+            Some(line) => line.trim_end_matches('\r'),
+            None => return header,
+        };
+        let column = (self.span.column - 1).max(0) as usize;
+        // Keep tabs to align the caret with the source line
+        let indent: String = line
+            .chars()
+            .take(column)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = line
+            .chars()
+            .skip(column)
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .count()
+            .max(1);
+        let lineno = self.span.line.to_string();
+        let pad = " ".repeat(lineno.len());
+        format!(
+            "{}\n{} |\n{} | {}\n{} | {}{}",
+            header,
+            pad,
+            lineno,
+            line,
+            pad,
+            indent,
+            "^".repeat(width)
+        )
+    }
+
+    /// Render with reading the source from the file of the span
+    ///
+    /// Falls back to the header line if the file cannot be read.
+    pub fn render_file(&self) -> String {
+        match self
+            .span
+            .file
+            .as_ref()
+            .and_then(|f| fs::read_to_string(&**f).ok())
+        {
+            Some(source) => self.render(&source),
+            None => self.to_string(),
+        }
+    }
+}
+
+fn quote<R: fmt::Display>(info: &easy::Info<char, R>) -> String {
+    match info {
+        easy::Info::Token(c) => format!("`{}`", c.escape_default()),
+        easy::Info::Range(r) => format!("`{}`", r),
+        easy::Info::Owned(s) => s.clone(),
+        easy::Info::Borrowed(s) => s.to_string(),
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.span, self.level, self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Errors reported together, e.g. all syntax errors in a file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, diag) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diag)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+/// Return from the function with an error diagnostic at `span`, like `failure::bail!`
+macro_rules! bail_at {
+    ($span:expr, $($arg:tt)*) => {
+        return Err($crate::diagnostics::Diagnostic::error($span, format!($($arg)*)).into())
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::fbs;
+    use combine::{stream::state::State, Parser};
+
+    fn span(line: i32, column: i32) -> Span {
+        Span {
+            file: None,
+            line,
+            column,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let source = "namespace A;\n\troot_type FooBaz;\n";
+        let diag = Diagnostic::error(&span(2, 12), "Cannot find table: FooBaz");
+        assert_eq!(
+            diag.render(source),
+            "2:12: error: Cannot find table: FooBaz\n  |\n2 | \troot_type FooBaz;\n  | \t          ^^^^^^"
+        );
+        let diag = Diagnostic::warning(&span(1, 12), "semicolon");
+        assert_eq!(
+            diag.render(source),
+            "1:12: warning: semicolon\n  |\n1 | namespace A;\n  |            ^"
+        );
+        // out of source
+        let diag = Diagnostic::error(&span(5, 1), "EOF");
+        assert_eq!(diag.render(source), "5:1: error: EOF");
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let input = State::with_positioner("table A {\n  a: int\n}", Span::new("a.fbs"));
+        let err = fbs().skip(combine::eof()).easy_parse(input).unwrap_err();
+        let diag = Diagnostic::from_parse_error(err);
+        assert_eq!(diag.span.to_string(), "a.fbs:3:1");
+        // Whitespaces and comments are not listed as expected
+        assert_eq!(diag.message, "unexpected `}`; expected `;`");
+    }
+}
@@ -0,0 +1,251 @@
+//! Memory layout of structs, which is same as `flatc`
+//!
+//! Fields are placed in the declaration order, each aligned to its own alignment
+//! with padding inserted before it. The struct is padded at the end to a multiple
+//! of its alignment, which is the largest one of the fields or `force_align`.
+
+use crate::{
+    parser::Type,
+    schema::{Schema, StructId, Symbol, TypeRef},
+};
+use failure::*;
+
+/// Placement of a field in the struct
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FieldLayout {
+    /// Offset from the beginning of the struct
+    pub offset: usize,
+    pub size: usize,
+    /// Padding bytes following the field
+    pub padding: usize,
+}
+
+/// Placement of fields, and size and alignment of the whole struct
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StructLayout {
+    pub size: usize,
+    pub align: usize,
+    pub fields: Vec<FieldLayout>,
+}
+
+/// Size of scalar type, or `None` for non-scalars
+pub fn scalar_size(ty: &Type) -> Option<usize> {
+    Some(match ty {
+        Type::Bool | Type::Int8 | Type::UInt8 => 1,
+        Type::Int16 | Type::UInt16 => 2,
+        Type::Int32 | Type::UInt32 | Type::Float32 => 4,
+        Type::Int64 | Type::UInt64 | Type::Float64 => 8,
+        _ => return None,
+    })
+}
+
+/// Padding bytes to align `offset` to `align`, which must be a power of two
+fn padding(offset: usize, align: usize) -> usize {
+    offset.wrapping_neg() & (align - 1)
+}
+
+/// Compute layouts of all structs, where nested structs are laid out first
+///
+/// `force_align` is the value of `force_align` attribute for each struct.
+pub(crate) fn struct_layouts(
+    schema: &Schema,
+    force_align: &[Option<usize>],
+) -> Fallible<Vec<StructLayout>> {
+    let mut builder = Builder {
+        schema,
+        force_align,
+        layouts: vec![None; schema.structs.len()],
+        visiting: Vec::new(),
+    };
+    for id in 0..schema.structs.len() {
+        builder.layout(StructId(id))?;
+    }
+    Ok(builder.layouts.into_iter().map(Option::unwrap).collect())
+}
+
+struct Builder<'a> {
+    schema: &'a Schema,
+    force_align: &'a [Option<usize>],
+    layouts: Vec<Option<StructLayout>>,
+    /// Structs being laid out, to detect recursive ones
+    visiting: Vec<StructId>,
+}
+
+impl<'a> Builder<'a> {
+    fn layout(&mut self, id: StructId) -> Fallible<&StructLayout> {
+        if self.layouts[id.0].is_none() {
+            let layout = self.compute(id)?;
+            self.layouts[id.0] = Some(layout);
+        }
+        Ok(self.layouts[id.0].as_ref().unwrap())
+    }
+
+    fn compute(&mut self, id: StructId) -> Fallible<StructLayout> {
+        let st = &self.schema.structs[id.0];
+        if self.visiting.contains(&id) {
+            bail_at!(&st.span, "Struct {} contains itself", st.name);
+        }
+        self.visiting.push(id);
+        let mut size = 0;
+        let mut align = 1;
+        let mut fields: Vec<FieldLayout> = Vec::new();
+        for field in &st.fields {
+            let (field_size, field_align) = self.size_align(&field.ty)?;
+            let pad = padding(size, field_align);
+            if let Some(last) = fields.last_mut() {
+                last.padding = pad;
+            }
+            size += pad;
+            fields.push(FieldLayout {
+                offset: size,
+                size: field_size,
+                padding: 0,
+            });
+            size += field_size;
+            align = align.max(field_align);
+        }
+        self.visiting.pop();
+
+        if let Some(force_align) = self.force_align[id.0] {
+            if force_align < align {
+                bail_at!(
+                    &st.span,
+                    "force_align of {} must not be less than its natural alignment {}: {}",
+                    st.name,
+                    align,
+                    force_align
+                );
+            }
+            align = force_align;
+        }
+        let pad = padding(size, align);
+        if let Some(last) = fields.last_mut() {
+            last.padding = pad;
+        }
+        Ok(StructLayout {
+            size: size + pad,
+            align,
+            fields,
+        })
+    }
+
+    /// Size and alignment of a member of struct, which is checked in [`Schema::new`]
+    fn size_align(&mut self, ty: &TypeRef) -> Fallible<(usize, usize)> {
+        Ok(match ty {
+            TypeRef::Builtin(ty) if scalar_size(ty).is_some() => {
+                let size = scalar_size(ty).unwrap();
+                (size, size)
+            }
+            TypeRef::Defined(Symbol::Enum(id)) => {
+                let ty = TypeRef::Builtin(self.schema.enums[id.0].ty.clone());
+                self.size_align(&ty)?
+            }
+            TypeRef::Defined(Symbol::Struct(id)) => {
+                let layout = self.layout(*id)?;
+                (layout.size, layout.align)
+            }
+            TypeRef::Array(ty, len) => {
+                let (size, align) = self.size_align(ty)?;
+                (size * *len as usize, align)
+            }
+            _ => unreachable!("Member of struct must be scalar, enum or struct"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{fbs, Span};
+    use combine::{stream::state::State, Parser};
+
+    fn layouts(input: &str) -> Fallible<Vec<StructLayout>> {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(input, Span::default()))
+            .unwrap();
+        Ok(Schema::new(&stmt)?
+            .structs
+            .into_iter()
+            .map(|st| st.layout)
+            .collect())
+    }
+
+    fn offsets(layout: &StructLayout) -> Vec<usize> {
+        layout.fields.iter().map(|f| f.offset).collect()
+    }
+
+    #[test]
+    fn test_padding() {
+        assert_eq!(padding(0, 8), 0);
+        assert_eq!(padding(5, 4), 3);
+        assert_eq!(padding(8, 8), 0);
+        assert_eq!(padding(9, 1), 0);
+    }
+
+    #[test]
+    fn test_struct_layout() {
+        // Same as `monster_test.fbs`
+        let layouts = layouts(
+            "enum Color : ubyte (bit_flags) { Red, Green, Blue }
+             struct Test { a: short; b: byte; }
+             struct Vec3 (force_align: 16) {
+                 x: float; y: float; z: float; test1: double; test2: Color; test3: Test;
+             }
+             struct Ability { id: uint; distance: uint; }
+             struct StructOfStructs { a: Ability; b: Test; c: Ability; }",
+        )
+        .unwrap();
+        let test = &layouts[0];
+        assert_eq!((test.size, test.align), (4, 2));
+        assert_eq!(
+            test.fields,
+            [
+                FieldLayout {
+                    offset: 0,
+                    size: 2,
+                    padding: 0
+                },
+                FieldLayout {
+                    offset: 2,
+                    size: 1,
+                    padding: 1
+                },
+            ]
+        );
+        let vec3 = &layouts[1];
+        assert_eq!((vec3.size, vec3.align), (32, 16));
+        assert_eq!(offsets(vec3), [0, 4, 8, 16, 24, 26]);
+        let paddings: Vec<_> = vec3.fields.iter().map(|f| f.padding).collect();
+        assert_eq!(paddings, [0, 0, 4, 0, 1, 2]);
+        let sos = &layouts[3];
+        assert_eq!((sos.size, sos.align), (20, 4));
+        assert_eq!(offsets(sos), [0, 8, 12]);
+    }
+
+    #[test]
+    fn test_array_layout() {
+        // Same as `arrays_test.fbs`
+        let layouts = layouts(
+            "enum TestEnum : byte { A, B, C }
+             struct NestedStruct { a: [int:2]; b: TestEnum; c: [TestEnum:2]; d: [long:2]; }
+             struct ArrayStruct {
+                 a: float; b: [int:0xF]; c: byte; d: [NestedStruct:2]; e: int32; f: [int64:2];
+             }",
+        )
+        .unwrap();
+        assert_eq!((layouts[0].size, layouts[0].align), (32, 8));
+        assert_eq!(offsets(&layouts[0]), [0, 8, 9, 16]);
+        assert_eq!((layouts[1].size, layouts[1].align), (160, 8));
+        assert_eq!(offsets(&layouts[1]), [0, 4, 64, 72, 136, 144]);
+    }
+
+    #[test]
+    fn test_layout_errors() {
+        let err = layouts("struct A { b: B; } struct B { a: A; }").unwrap_err();
+        assert_eq!(err.to_string(), "1:1: error: Struct A contains itself");
+        let err = layouts("struct S (force_align: 4) { x: double; }").unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("force_align of S must not be less than its natural alignment 8: 4"));
+    }
+}
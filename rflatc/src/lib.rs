@@ -1,7 +1,7 @@
+#[macro_use]
+pub mod diagnostics;
+pub mod layout;
+pub mod loader;
 pub mod parser;
+pub mod schema;
 pub mod semantics;
-
-pub fn remove_comment(input: &str) -> String {
-    let re = regex::Regex::new(r"//.*\n").unwrap();
-    re.replace_all(&input, "").to_string()
-}
@@ -0,0 +1,321 @@
+//! Load schema files resolving `include` statements
+
+use crate::{
+    diagnostics::{Diagnostic, Diagnostics},
+    parser::*,
+    semantics::qualified_names,
+};
+use combine::stream::state::State;
+use failure::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Statements parsed from a schema file
+#[derive(Debug)]
+pub struct Source {
+    pub path: PathBuf,
+    pub stmt: Vec<Stmt>,
+}
+
+/// Parse a schema, where `file` is used for the spans of AST nodes
+///
+/// Broken statements are skipped, and the rest of the schema is parsed
+/// to return all syntax errors with the statements parsed successfully.
+pub fn parse_str(input: &str, file: &Path) -> (Vec<Stmt>, Vec<Diagnostic>) {
+    let (stmt, errors) = fbs_recover(State::with_positioner(input, Span::new(file)));
+    let errors = errors
+        .into_iter()
+        .map(Diagnostic::from_parse_error)
+        .collect();
+    (stmt, errors)
+}
+
+/// Parse a schema file, failing with all syntax errors as [`Diagnostics`]
+pub fn parse_file(path: &Path) -> Fallible<Vec<Stmt>> {
+    let input = fs::read_to_string(path)
+        .map_err(|e| format_err!("Cannot read {}: {}", path.display(), e))?;
+    let (stmt, errors) = parse_str(&input, path);
+    if !errors.is_empty() {
+        return Err(Diagnostics(errors).into());
+    }
+    Ok(stmt)
+}
+
+struct Loader<'a> {
+    include_paths: &'a [PathBuf],
+    sources: Vec<Source>,
+    /// Files currently being loaded, which are treated as already included
+    /// so that a file including itself (directly or not) is skipped
+    stack: Vec<PathBuf>,
+}
+
+impl<'a> Loader<'a> {
+    fn load(&mut self, path: PathBuf) -> Fallible<()> {
+        if self.stack.contains(&path) || self.sources.iter().any(|src| src.path == path) {
+            return Ok(());
+        }
+        let stmt = parse_file(&path)?;
+        self.load_stmt(path, stmt)
+    }
+
+    /// Load files included from `stmt` parsed from `path`, and then add it to sources
+    fn load_stmt(&mut self, path: PathBuf, stmt: Vec<Stmt>) -> Fallible<()> {
+        self.stack.push(path.clone());
+        for st in &stmt {
+            if let Stmt::Include(include, span) = st {
+                let include = self.resolve(&path, include, span)?;
+                self.load(include)?;
+            }
+        }
+        self.stack.pop();
+        self.sources.push(Source { path, stmt });
+        Ok(())
+    }
+
+    /// Search the directory of including file, and then include paths
+    fn resolve(&self, from: &Path, include: &str, span: &Span) -> Fallible<PathBuf> {
+        let dirs = from
+            .parent()
+            .into_iter()
+            .chain(self.include_paths.iter().map(|p| p.as_path()));
+        for dir in dirs {
+            let path = dir.join(include);
+            if path.is_file() {
+                return Ok(path.canonicalize()?);
+            }
+        }
+        bail_at!(span, "Cannot find included file {}", include)
+    }
+}
+
+/// Load a schema file and all files included from it
+///
+/// Sources are sorted so that included files come before the files including them,
+/// i.e. the last one is the file specified by `path`. Each file is loaded only once,
+/// and including a file already loaded or being loaded, e.g. itself, is ignored.
+pub fn load(path: &Path, include_paths: &[PathBuf]) -> Fallible<Vec<Source>> {
+    let path = path
+        .canonicalize()
+        .map_err(|e| format_err!("Cannot open {}: {}", path.display(), e))?;
+    let mut loader = Loader {
+        include_paths,
+        sources: Vec::new(),
+        stack: Vec::new(),
+    };
+    loader.load(path)?;
+    Ok(loader.sources)
+}
+
+/// Load a schema given as a string, e.g. read from stdin, and all files included from it
+///
+/// `file` is used for the spans as [`parse_str`], and included files are searched in its
+/// directory, i.e. the current directory for `<stdin>`, and then include paths.
+/// Sources are sorted as [`load`].
+pub fn load_str(input: &str, file: &Path, include_paths: &[PathBuf]) -> Fallible<Vec<Source>> {
+    let (stmt, errors) = parse_str(input, file);
+    if !errors.is_empty() {
+        return Err(Diagnostics(errors).into());
+    }
+    let mut loader = Loader {
+        include_paths,
+        sources: Vec::new(),
+        stack: Vec::new(),
+    };
+    loader.load_stmt(file.to_path_buf(), stmt)?;
+    Ok(loader.sources)
+}
+
+/// Merge statements of loaded sources into one schema
+///
+/// `include` statements are dropped, and `root_type`, `file_identifier` and
+/// `file_extension` are taken only from the last (i.e. not included) file as `flatc` does.
+/// The namespace is reset to the root one at the head of each file.
+pub fn merge(sources: &[Source]) -> Vec<Stmt> {
+    let last = sources.len().saturating_sub(1);
+    sources
+        .iter()
+        .enumerate()
+        .flat_map(|(i, src)| {
+            let reset = Stmt::Namespace(Vec::new(), Span::new(&src.path));
+            let stmt = src
+                .stmt
+                .iter()
+                .filter(move |st| match st {
+                    Stmt::Include(..) => false,
+                    Stmt::Root(..) | Stmt::FileIdentifier(..) | Stmt::FileExtension(..) => {
+                        i == last
+                    }
+                    _ => true,
+                })
+                .cloned();
+            Some(reset).into_iter().chain(stmt)
+        })
+        .collect()
+}
+
+/// Seek the file where the type of the fully-qualified `name` is declared
+pub fn origin<'a>(sources: &'a [Source], name: &str) -> Option<&'a Path> {
+    sources
+        .iter()
+        .find(|src| {
+            qualified_names(&src.stmt)
+                .iter()
+                .any(|(qualified, _)| qualified == name)
+        })
+        .map(|src| src.path.as_path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantics::analyze;
+
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rflatc-loader-{}", name));
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = write_files(
+            "load",
+            &[
+                (
+                    "main.fbs",
+                    r#"include "a.fbs"; include "b.fbs"; namespace M;
+                       table Root { a: A; b: B; } root_type Root;"#,
+                ),
+                ("a.fbs", r#"include "common.fbs"; table A { c: C; }"#),
+                ("b.fbs", r#"include "common.fbs"; table B { c: C; }"#),
+                ("inc/common.fbs", "table C { x: int; } root_type C;"),
+            ],
+        );
+        let sources = load(&dir.join("main.fbs"), &[dir.join("inc")]).unwrap();
+        let names: Vec<_> = sources
+            .iter()
+            .map(|src| src.path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["common.fbs", "a.fbs", "b.fbs", "main.fbs"]);
+        assert!(origin(&sources, "C").unwrap().ends_with("inc/common.fbs"));
+        assert!(origin(&sources, "M.Root").unwrap().ends_with("main.fbs"));
+        assert!(origin(&sources, "Root").is_none());
+
+        let schema = analyze(&merge(&sources)).unwrap();
+        let root = schema.root().unwrap();
+        assert_eq!(root.fields.len(), 2);
+        assert_eq!(root.name, "M.Root");
+    }
+
+    #[test]
+    fn test_namespace_per_file() {
+        let dir = write_files(
+            "namespace",
+            &[
+                (
+                    "main.fbs",
+                    r#"include "geometry.fbs"; table Root { v: Geometry.Vec3; } root_type Root;"#,
+                ),
+                (
+                    "geometry.fbs",
+                    "namespace Geometry; struct Vec3 { x: float; }",
+                ),
+            ],
+        );
+        let sources = load(&dir.join("main.fbs"), &[]).unwrap();
+        assert!(origin(&sources, "Geometry.Vec3")
+            .unwrap()
+            .ends_with("geometry.fbs"));
+        assert!(origin(&sources, "Vec3").is_none());
+        let schema = analyze(&merge(&sources)).unwrap();
+        let root = schema.root().unwrap();
+        assert_eq!(root.name, "Root");
+        assert!(root.namespace.is_empty());
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let dir = write_files(
+            "cycle",
+            &[
+                ("a.fbs", r#"include "b.fbs"; table A { x: int; }"#),
+                ("b.fbs", r#"include "a.fbs"; table B { x: int; }"#),
+            ],
+        );
+        let sources = load(&dir.join("a.fbs"), &[]).unwrap();
+        let names: Vec<_> = sources
+            .iter()
+            .map(|src| src.path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["b.fbs", "a.fbs"]);
+
+        let dir = write_files(
+            "self",
+            &[(
+                "a.fbs",
+                r#"include "a.fbs"; include "a.fbs"; table A { x: int; }"#,
+            )],
+        );
+        let sources = load(&dir.join("a.fbs"), &[]).unwrap();
+        assert_eq!(sources.len(), 1);
+        analyze(&merge(&sources)).unwrap();
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let dir = write_files(
+            "syntax_errors",
+            &[(
+                "a.fbs",
+                "table A { x: int }\ntable B { y: int; }\nroot_type B\n",
+            )],
+        );
+        let err = load(&dir.join("a.fbs"), &[]).unwrap_err();
+        let diags = err.downcast::<Diagnostics>().unwrap();
+        let spans: Vec<_> = diags
+            .0
+            .iter()
+            .map(|d| (d.span.line, d.span.column))
+            .collect();
+        assert_eq!(spans, [(1, 18), (4, 1)]);
+
+        let (stmt, errors) = parse_str(
+            "table A { x: int }\ntable B { y: int; }",
+            Path::new("a.fbs"),
+        );
+        assert_eq!(stmt.len(), 1);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_include_not_found() {
+        let dir = write_files("not_found", &[("a.fbs", "table A {}\ninclude \"b.fbs\";")]);
+        let err = load(&dir.join("a.fbs"), &[]).unwrap_err();
+        let diag = err.downcast::<Diagnostic>().unwrap();
+        assert_eq!((diag.span.line, diag.span.column), (2, 1));
+        assert_eq!(diag.message, "Cannot find included file b.fbs");
+    }
+
+    #[test]
+    fn test_load_str() {
+        let dir = write_files("str", &[("inc/common.fbs", "table C { x: int; }")]);
+        let input = r#"include "common.fbs"; table Root { c: C; } root_type Root;"#;
+        let sources = load_str(input, Path::new("<stdin>"), &[dir.join("inc")]).unwrap();
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[1].path, Path::new("<stdin>"));
+        let schema = analyze(&merge(&sources)).unwrap();
+        assert_eq!(schema.root().unwrap().name, "Root");
+
+        let err = load_str(input, Path::new("<stdin>"), &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "<stdin>:1:1: error: Cannot find included file common.fbs"
+        );
+    }
+}
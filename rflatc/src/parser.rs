@@ -2,53 +2,205 @@
 //!
 //! - [Grammar of the schema language](https://google.github.io/flatbuffers/flatbuffers_grammar.html)
 
-use combine::{char::*, parser::Parser, *};
+use combine::{
+    char::*,
+    error::StreamError,
+    parser::Parser,
+    stream::{
+        easy,
+        state::{Positioner, State},
+        Resetable, StreamErrorFor,
+    },
+    *,
+};
+use std::{
+    convert::TryFrom,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 pub type Identifier = String;
 
+/// Location in a schema file, tracked by `combine::stream::state::State` while parsing
+/// and recorded on AST nodes
+///
+/// ```
+/// use combine::{stream::state::State, Parser};
+/// use rflatc::parser::{fbs, Span};
+///
+/// let input = State::with_positioner("root_type A;", Span::new("a.fbs"));
+/// let (stmt, _) = fbs().parse(input).unwrap();
+/// assert_eq!(stmt[0].span().to_string(), "a.fbs:1:1");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    pub file: Option<Arc<PathBuf>>,
+    pub line: i32,
+    pub column: i32,
+}
+
+impl Span {
+    pub fn new<P: AsRef<Path>>(file: P) -> Self {
+        Span {
+            file: Some(Arc::new(file.as_ref().to_path_buf())),
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl Default for Span {
+    fn default() -> Self {
+        Span {
+            file: None,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl Positioner<char> for Span {
+    type Position = Span;
+
+    fn position(&self) -> Span {
+        self.clone()
+    }
+
+    fn update(&mut self, c: &char) {
+        self.column += 1;
+        if *c == '\n' {
+            self.column = 1;
+            self.line += 1;
+        }
+    }
+}
+
+impl Resetable for Span {
+    type Checkpoint = Self;
+
+    fn checkpoint(&self) -> Self {
+        self.clone()
+    }
+
+    fn reset(&mut self, checkpoint: Self) {
+        *self = checkpoint;
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Line comment except doc comment, i.e. `// ...` but not `/// ...`
+fn line_comment<I>() -> impl Parser<Input = I, Output = ()>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    attempt(string("//").skip(not_followed_by(token('/')))).with(skip_many(satisfy(|c| c != '\n')))
+}
+
+/// block_comment = /* .* */
+fn block_comment<I>() -> impl Parser<Input = I, Output = ()>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    attempt(string("/*"))
+        .with(skip_many(
+            satisfy(|c| c != '*').or(attempt(token('*').skip(not_followed_by(token('/'))))),
+        ))
+        .skip(string("*/"))
+}
+
+/// Skip whitespaces and comments, but stop at doc comment
+///
+/// They are silent not to be listed as expected tokens in syntax errors.
+fn blank<I>() -> impl Parser<Input = I, Output = ()>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    skip_many(choice((
+        space().map(|_| ()),
+        line_comment(),
+        block_comment(),
+    )))
+    .silent()
+}
+
+/// doc_line = /// .*
+fn doc_line<I>() -> impl Parser<Input = I, Output = String>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    attempt(string("///"))
+        .with(many(satisfy(|c| c != '\n' && c != '\r')))
+        .skip(blank())
+        .silent()
+}
+
+/// Skip whitespaces and all comments including doc comments
+fn blank_all<I>() -> impl Parser<Input = I, Output = ()>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    blank().with(skip_many(doc_line()))
+}
+
+/// Doc comments attached to the following declaration
+///
+/// Doc comments at the end of block or input are not attached to anything, and skipped.
+fn doc_comment<I>() -> impl Parser<Input = I, Output = Vec<String>>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    optional(attempt(many1(doc_line()).skip(not_followed_by(
+        token('}').map(|_| "}").or(eof().map(|_| "end of input")),
+    ))))
+    .map(Option::unwrap_or_default)
+}
+
 fn paren<I, F>(f: F) -> impl Parser<Input = I, Output = F::Output>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
     F: Parser<Input = I>,
 {
-    between(
-        token('{'),
-        token('}'),
-        spaces().and(f).skip(spaces()).map(|x| x.1),
-    )
+    between(token('{'), token('}'), blank().with(f).skip(blank_all()))
 }
 
 fn brace<I, F>(f: F) -> impl Parser<Input = I, Output = F::Output>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
     F: Parser<Input = I>,
 {
-    between(
-        token('('),
-        token(')'),
-        spaces().and(f).skip(spaces()).map(|x| x.1),
-    )
+    between(token('('), token(')'), blank().with(f).skip(blank()))
 }
 
-fn quoted<I, F>(f: F) -> impl Parser<Input = I, Output = F::Output>
+/// Keyword which must not be followed by identifier characters
+fn keyword<I>(word: &'static str) -> impl Parser<Input = I, Output = &'static str>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
-    F: Parser<Input = I>,
 {
-    between(
-        token('"'),
-        token('"'),
-        spaces().and(f).skip(spaces()).map(|x| x.1),
-    )
+    attempt(string(word).skip(not_followed_by(alpha_num().or(char('_')))))
 }
 
 /// ident = [a-zA-Z_][a-zA-Z0-9_]*
 fn identifier<I>() -> impl Parser<Input = I, Output = Identifier>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
     letter()
@@ -56,40 +208,195 @@ where
         .map(|(l, a)| format!("{}{}", l, a.iter().collect::<String>()))
 }
 
-fn integer<I>() -> impl Parser<Input = I, Output = i64>
+/// Type name which may be qualified by namespaces, e.g. `Geometry.Vec3`
+fn qualified_identifier<I>() -> impl Parser<Input = I, Output = Identifier>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    optional(token('-'))
-        .skip(spaces())
-        .and(many1(digit()).map(|d: Vec<char>| d.into_iter().collect()))
-        .map(|(m, d)| {
-            match m {
-                Some(_) => format!("-{}", d),
-                None => d,
-            }
-            .parse()
-            .unwrap()
-        })
+    sep_by1::<Vec<Identifier>, _, _>(identifier(), token('.')).map(|ids| ids.join("."))
 }
 
+/// Integer wide enough for both `long` and `ulong` constants
+pub type Integer = i128;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Scalar {
     Identifier(Identifier),
-    Integer(i64),
+    Integer(Integer),
     Float(f64),
+    Bool(bool),
+    /// `null` default of optional scalar fields
+    Null,
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Scalar::Identifier(id) => write!(f, "{}", id),
+            Scalar::Integer(n) => write!(f, "{}", n),
+            Scalar::Float(x) => write!(f, "{}", x),
+            Scalar::Bool(b) => write!(f, "{}", b),
+            Scalar::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// Convert `[:digit:]+ ( . [:digit:]* )? ( [eE] [-+]? [:digit:]+ )?` into a number
+fn dec_number(
+    int: String,
+    frac: Option<String>,
+    exp: Option<String>,
+) -> Result<Scalar, &'static str> {
+    if int.is_empty() && frac.as_ref().map(|f| f.is_empty()).unwrap_or(true) {
+        return Err("expected digits");
+    }
+    if frac.is_none() && exp.is_none() {
+        return int
+            .parse::<u64>()
+            .map(|n| Scalar::Integer(n.into()))
+            .map_err(|_| "integer constant overflows 64-bit");
+    }
+    format!(
+        "{}.{}{}",
+        int,
+        frac.unwrap_or_default(),
+        exp.unwrap_or_default()
+    )
+    .parse()
+    .map(Scalar::Float)
+    .map_err(|_| "invalid float constant")
+}
+
+/// Convert `[:xdigit:]+ ( . [:xdigit:]* )? ( [pP] [-+]? [:digit:]+ )?` into a number
+fn hex_number(
+    int: String,
+    frac: Option<String>,
+    exp: Option<String>,
+) -> Result<Scalar, &'static str> {
+    if int.is_empty() && frac.as_ref().map(|f| f.is_empty()).unwrap_or(true) {
+        return Err("expected hexadecimal digits");
+    }
+    match (frac, exp) {
+        (None, None) => u64::from_str_radix(&int, 16)
+            .map(|n| Scalar::Integer(n.into()))
+            .map_err(|_| "integer constant overflows 64-bit"),
+        (_, None) => Err("hexadecimal float requires exponent"),
+        (frac, Some(exp)) => {
+            let frac = frac.unwrap_or_default();
+            let mantissa = int
+                .chars()
+                .chain(frac.chars())
+                .fold(0.0, |m, c| m * 16.0 + f64::from(c.to_digit(16).unwrap()));
+            let exp: i32 = exp[1..].parse().map_err(|_| "invalid exponent")?;
+            Ok(Scalar::Float(
+                mantissa * 2f64.powi(exp - 4 * frac.len() as i32),
+            ))
+        }
+    }
+}
+
+/// Apply sign to number parsed without sign
+fn signed(sign: Option<char>, value: Scalar) -> Result<Scalar, &'static str> {
+    match (sign, value) {
+        (Some('-'), Scalar::Integer(n)) if n > Integer::from(i64::MAX) + 1 => {
+            Err("integer constant overflows 64-bit")
+        }
+        (Some('-'), Scalar::Integer(n)) => Ok(Scalar::Integer(-n)),
+        (Some('-'), Scalar::Float(x)) => Ok(Scalar::Float(-x)),
+        (Some(_), Scalar::Identifier(_)) | (Some(_), Scalar::Bool(_)) | (Some(_), Scalar::Null) => {
+            Err("sign is only allowed for numbers")
+        }
+        (_, value) => Ok(value),
+    }
+}
+
+/// Decimal or hexadecimal, integer or float number without sign
+fn unsigned_number<I>() -> impl Parser<Input = I, Output = Scalar>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    let exponent = |e: &'static str| {
+        one_of(e.chars())
+            .and(optional(one_of("+-".chars())))
+            .and(many1::<String, _>(digit()))
+            .map(|((e, sign), digits): ((char, Option<char>), String)| {
+                let mut exp = e.to_string();
+                exp.extend(sign);
+                exp + &digits
+            })
+    };
+    let hex = attempt(token('0').and(one_of("xX".chars())))
+        .with(many::<String, _>(hex_digit()))
+        .and(optional(token('.').with(many::<String, _>(hex_digit()))))
+        .and(optional(exponent("pP")))
+        .and_then(|((int, frac), exp)| {
+            hex_number(int, frac, exp).map_err(StreamErrorFor::<I>::message_static_message)
+        });
+    let dec = many::<String, _>(digit())
+        .and(optional(token('.').with(many::<String, _>(digit()))))
+        .and(optional(exponent("eE")))
+        .and_then(|((int, frac), exp)| {
+            dec_number(int, frac, exp).map_err(StreamErrorFor::<I>::message_static_message)
+        });
+    hex.or(dec)
 }
 
+/// integer_constant = dec_integer_constant | hex_integer_constant
+fn integer<I>() -> impl Parser<Input = I, Output = Integer>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    optional(one_of("+-".chars()))
+        .skip(blank())
+        .and(unsigned_number())
+        .and_then(|(sign, value)| match signed(sign, value) {
+            Ok(Scalar::Integer(n)) => Ok(n),
+            Ok(_) => Err(StreamErrorFor::<I>::message_static_message(
+                "expected integer constant",
+            )),
+            Err(e) => Err(StreamErrorFor::<I>::message_static_message(e)),
+        })
+}
+
+/// scalar = boolean_constant | integer_constant | float_constant | ident | null
+///
+/// float_constant includes special values, i.e. `[-+]?(nan|inf|infinity)`
 fn scalar<I>() -> impl Parser<Input = I, Output = Scalar>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    choice((
-        integer().map(|x| Scalar::Integer(x)),
-        identifier().map(|x| Scalar::Identifier(x)),
-    ))
+    let word = identifier().map(|id| match id.as_str() {
+        "true" => Scalar::Bool(true),
+        "false" => Scalar::Bool(false),
+        "null" => Scalar::Null,
+        "nan" => Scalar::Float(f64::NAN),
+        "inf" | "infinity" => Scalar::Float(f64::INFINITY),
+        _ => Scalar::Identifier(id),
+    });
+    optional(one_of("+-".chars()))
+        .skip(blank())
+        .and(word.or(unsigned_number()))
+        .and_then(|(sign, value)| {
+            signed(sign, value).map_err(StreamErrorFor::<I>::message_static_message)
+        })
+}
+
+/// Length of fixed-length array, which must be in 1..=65535
+fn array_length<I>() -> impl Parser<Input = I, Output = u16>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    integer().and_then(|n| match u16::try_from(n) {
+        Ok(0) | Err(_) => Err(StreamErrorFor::<I>::message_static_message(
+            "array length must be in 1..=65535",
+        )),
+        Ok(len) => Ok(len),
+    })
 }
 
 /// Use obviously sized type names
@@ -107,18 +414,56 @@ pub enum Type {
     Float32,
     Float64,
     String_,
+    Vector(Box<Type>),
+    /// Fixed-length array, only allowed in struct
+    Array(Box<Type>, u16),
     UserDefined(Identifier),
 }
 
+/// Spelling in schema, e.g. `byte` and `[float:3]`
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Bool => write!(f, "bool"),
+            Type::Int8 => write!(f, "byte"),
+            Type::UInt8 => write!(f, "ubyte"),
+            Type::Int16 => write!(f, "short"),
+            Type::UInt16 => write!(f, "ushort"),
+            Type::Int32 => write!(f, "int"),
+            Type::UInt32 => write!(f, "uint"),
+            Type::Int64 => write!(f, "long"),
+            Type::UInt64 => write!(f, "ulong"),
+            Type::Float32 => write!(f, "float"),
+            Type::Float64 => write!(f, "double"),
+            Type::String_ => write!(f, "string"),
+            Type::Vector(ty) => write!(f, "[{}]", ty),
+            Type::Array(ty, len) => write!(f, "[{}:{}]", ty, len),
+            Type::UserDefined(id) => write!(f, "{}", id),
+        }
+    }
+}
+
 /// type = bool | byte | ubyte | short | ushort | int | uint | float | long | ulong | double | int8
 /// | uint8 | int16 | uint16 | int32 | uint32| int64 | uint64 | float32 | float64 | string |
-/// [ type ] | ident
+/// [ type ] | [ type : integer_constant ] | ident ( . ident )*
 fn ty<I>() -> impl Parser<Input = I, Output = Type>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    identifier().map(|id| match id.as_ref() {
+    let vector = token('[')
+        .skip(blank())
+        .with(parser(|input: &mut I| ty().parse_stream(input)))
+        .skip(blank())
+        .and(optional(
+            token(':').skip(blank()).with(array_length()).skip(blank()),
+        ))
+        .skip(token(']'))
+        .map(|(ty, len)| match len {
+            Some(len) => Type::Array(Box::new(ty), len),
+            None => Type::Vector(Box::new(ty)),
+        });
+    let named = qualified_identifier().map(|id| match id.as_ref() {
         "bool" => Type::Bool,
         "byte" => Type::Int8,
         "ubyte" => Type::UInt8,
@@ -141,79 +486,178 @@ where
         "float32" => Type::Float32,
         "float64" => Type::Float64,
         "string" => Type::String_,
-        _ => Type::UserDefined(id.into()),
-    })
+        _ => Type::UserDefined(id),
+    });
+    vector.or(named)
+}
+
+/// Character in string constant, where `\uXXXX` escape is kept as UTF-16 code unit
+enum StrChar {
+    Char(char),
+    Utf16(u16),
+}
+
+/// escape = \ ( " | \ | / | b | f | n | r | t | x [:xdigit:]{2} | u [:xdigit:]{4} )
+fn escape<I>() -> impl Parser<Input = I, Output = StrChar>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    let hex = |n| {
+        count_min_max::<String, _>(n, n, hex_digit()).map(|d| u32::from_str_radix(&d, 16).unwrap())
+    };
+    token('\\').with(choice((
+        one_of("\"\\/".chars()).map(StrChar::Char),
+        token('b').map(|_| StrChar::Char('\u{8}')),
+        token('f').map(|_| StrChar::Char('\u{c}')),
+        token('n').map(|_| StrChar::Char('\n')),
+        token('r').map(|_| StrChar::Char('\r')),
+        token('t').map(|_| StrChar::Char('\t')),
+        token('x')
+            .with(hex(2))
+            .map(|c| StrChar::Char(std::char::from_u32(c).unwrap())),
+        token('u').with(hex(4)).map(|c| StrChar::Utf16(c as u16)),
+    )))
+}
+
+/// Decode characters with combining UTF-16 surrogate pairs
+fn decode_str(chars: Vec<StrChar>) -> Result<String, &'static str> {
+    let units = chars.into_iter().flat_map(|c| match c {
+        StrChar::Char(c) => c.encode_utf16(&mut [0; 2]).to_vec(),
+        StrChar::Utf16(u) => vec![u],
+    });
+    std::char::decode_utf16(units)
+        .collect::<Result<_, _>>()
+        .map_err(|_| "invalid UTF-16 surrogate in \\u escape")
 }
 
-pub type Metadata = Vec<String>;
+/// string_constant = \" ( char | escape )* \"
+fn string_constant<I>() -> impl Parser<Input = I, Output = String>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    between(
+        token('"'),
+        token('"'),
+        many(escape().or(satisfy(|c| c != '"' && c != '\\').map(StrChar::Char))),
+    )
+    .and_then(|chars| decode_str(chars).map_err(StreamErrorFor::<I>::message_static_message))
+}
+
+/// Value of attribute in metadata
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Scalar(Scalar),
+    String(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Scalar(scalar) => write!(f, "{}", scalar),
+            Value::String(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+/// single_value = scalar | string_constant
+fn single_value<I>() -> impl Parser<Input = I, Output = Value>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    choice((
+        string_constant().map(Value::String),
+        scalar().map(Value::Scalar),
+    ))
+}
+
+/// Attributes with optional values, e.g. `(id: 3, key, hash: "fnv1_32")`
+pub type Metadata = Vec<(Identifier, Option<Value>)>;
 
 /// metadata = [ ( commasep( ident [ : single_value ] ) ) ]
 fn metadata<I>() -> impl Parser<Input = I, Output = Metadata>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    brace(sep_by1(
-        string("deprecated").map(|x| x.to_string()),
-        token(',').skip(spaces()),
-    ))
+    let attribute = identifier().skip(blank()).and(optional(
+        token(':').skip(blank()).with(single_value()).skip(blank()),
+    ));
+    brace(sep_by(attribute, token(',').skip(blank())))
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Field {
     pub id: Identifier,
     pub ty: Type,
-    pub scalar: Option<Scalar>,
+    /// Default value, which may be a string of names of `bit_flags` enum, e.g. `"A B"`
+    pub default: Option<Value>,
     pub metadata: Option<Metadata>,
+    /// Doc comments, i.e. `/// ...` lines preceding the declaration
+    pub doc: Vec<String>,
+    pub span: Span,
 }
 
-/// field_decl = ident : type [ = scalar ] metadata ;
+/// field_decl = ident : type [ = single_value ] metadata ;
 fn field<I>() -> impl Parser<Input = I, Output = Field>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    identifier()
-        .skip(spaces())
+    doc_comment()
+        .and(position())
+        .and(identifier())
+        .skip(blank())
         .skip(token(':'))
-        .skip(spaces())
+        .skip(blank())
         .and(ty())
-        .skip(spaces())
+        .skip(blank())
         .and(optional(
-            token('=').skip(spaces()).and(scalar()).map(|x| x.1),
+            token('=').skip(blank()).and(single_value()).map(|x| x.1),
         ))
-        .skip(spaces())
+        .skip(blank())
         .and(optional(metadata()))
-        .skip(spaces())
+        .skip(blank())
         .skip(token(';'))
-        .skip(spaces())
-        .map(|(((id, ty), scalar), metadata)| Field {
+        .skip(blank())
+        .map(|(((((doc, span), id), ty), default), metadata)| Field {
             id,
             ty,
-            scalar: scalar,
+            default,
             metadata,
+            doc,
+            span,
         })
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct EnumVal {
     pub id: Identifier,
-    pub integer_constant: Option<i64>,
+    pub integer_constant: Option<Integer>,
+    /// Doc comments, i.e. `/// ...` lines preceding the declaration
+    pub doc: Vec<String>,
+    pub span: Span,
 }
 
 /// enumval_decl = ident [ = integer_constant ]
 fn enumval<I>() -> impl Parser<Input = I, Output = EnumVal>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    identifier()
-        .skip(spaces())
-        .and(optional(token('=').skip(spaces()).and(integer())))
-        .skip(spaces())
-        .map(|(id, val)| EnumVal {
+    doc_comment()
+        .and(position())
+        .and(identifier())
+        .skip(blank())
+        .and(optional(token('=').skip(blank()).and(integer())))
+        .skip(blank())
+        .map(|(((doc, span), id), val)| EnumVal {
             id,
             integer_constant: val.map(|(_, v)| v),
+            doc,
+            span,
         })
 }
 
@@ -222,213 +666,842 @@ pub struct Enum {
     pub id: Identifier,
     pub ty: Option<Type>,
     pub values: Vec<EnumVal>,
+    pub metadata: Option<Metadata>,
+    /// Doc comments, i.e. `/// ...` lines preceding the declaration
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+/// Member of union, which may be aliased as `Alias: TableName`
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnionVal {
+    pub alias: Option<Identifier>,
+    pub ty: Identifier,
+    /// Doc comments, i.e. `/// ...` lines preceding the declaration
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+/// unionval_decl = [ ident : ] ident
+fn unionval<I>() -> impl Parser<Input = I, Output = UnionVal>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    doc_comment()
+        .and(position())
+        .and(qualified_identifier())
+        .skip(blank())
+        .and(optional(
+            token(':')
+                .skip(blank())
+                .and(qualified_identifier())
+                .map(|x| x.1),
+        ))
+        .skip(blank())
+        .and_then(|(((doc, span), id), ty)| match ty {
+            Some(_) if id.contains('.') => Err(StreamErrorFor::<I>::message_static_message(
+                "alias of union member cannot be qualified",
+            )),
+            Some(ty) => Ok(UnionVal {
+                alias: Some(id),
+                ty,
+                doc,
+                span,
+            }),
+            None => Ok(UnionVal {
+                alias: None,
+                ty: id,
+                doc,
+                span,
+            }),
+        })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Union {
+    pub id: Identifier,
+    pub values: Vec<UnionVal>,
+    pub metadata: Option<Metadata>,
+    /// Doc comments, i.e. `/// ...` lines preceding the declaration
+    pub doc: Vec<String>,
+    pub span: Span,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Table {
     pub id: Identifier,
     pub fields: Vec<Field>,
+    pub metadata: Option<Metadata>,
+    /// Doc comments, i.e. `/// ...` lines preceding the declaration
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Struct {
+    pub id: Identifier,
+    pub fields: Vec<Field>,
+    pub metadata: Option<Metadata>,
+    /// Doc comments, i.e. `/// ...` lines preceding the declaration
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RpcMethod {
+    pub id: Identifier,
+    pub request: Identifier,
+    pub response: Identifier,
+    pub metadata: Option<Metadata>,
+    /// Doc comments, i.e. `/// ...` lines preceding the declaration
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+/// rpc_method = ident ( ident ) : ident metadata ;
+fn rpc_method<I>() -> impl Parser<Input = I, Output = RpcMethod>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    doc_comment()
+        .and(position())
+        .and(identifier())
+        .skip(blank())
+        .and(brace(qualified_identifier()))
+        .skip(blank())
+        .skip(token(':'))
+        .skip(blank())
+        .and(qualified_identifier())
+        .skip(blank())
+        .and(optional(metadata()))
+        .skip(blank())
+        .skip(token(';'))
+        .skip(blank())
+        .map(
+            |(((((doc, span), id), request), response), metadata)| RpcMethod {
+                id,
+                request,
+                response,
+                metadata,
+                doc,
+                span,
+            },
+        )
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RpcService {
+    pub id: Identifier,
+    pub methods: Vec<RpcMethod>,
+    /// Doc comments, i.e. `/// ...` lines preceding the declaration
+    pub doc: Vec<String>,
+    pub span: Span,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Stmt {
-    Namespace(Vec<Identifier>),
-    FileIdentifier(Identifier),
-    Root(Identifier),
+    Include(String, Span),
+    Attribute(String, Span),
+    Namespace(Vec<Identifier>, Span),
+    FileIdentifier(String, Span),
+    FileExtension(String, Span),
+    Root(Identifier, Span),
     Table(Table),
+    Struct(Struct),
     Enum(Enum),
+    Union(Union),
+    RpcService(RpcService),
+}
+
+impl Stmt {
+    pub fn span(&self) -> &Span {
+        match self {
+            Stmt::Include(_, span)
+            | Stmt::Attribute(_, span)
+            | Stmt::Namespace(_, span)
+            | Stmt::FileIdentifier(_, span)
+            | Stmt::FileExtension(_, span)
+            | Stmt::Root(_, span) => span,
+            Stmt::Table(table) => &table.span,
+            Stmt::Struct(st) => &st.span,
+            Stmt::Enum(e) => &e.span,
+            Stmt::Union(union) => &union.span,
+            Stmt::RpcService(service) => &service.span,
+        }
+    }
+}
+
+/// include = include string_constant ;
+fn include<I>() -> impl Parser<Input = I, Output = Stmt>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    position()
+        .skip(keyword("include"))
+        .skip(blank())
+        .and(string_constant())
+        .skip(blank())
+        .skip(token(';'))
+        .skip(blank())
+        .map(|(span, x)| Stmt::Include(x, span))
+}
+
+/// attribute_decl = attribute ident | \"ident\" ;
+fn attribute<I>() -> impl Parser<Input = I, Output = Stmt>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    position()
+        .skip(keyword("attribute"))
+        .skip(blank())
+        .and(string_constant().or(identifier()))
+        .skip(blank())
+        .skip(token(';'))
+        .skip(blank())
+        .map(|(span, x)| Stmt::Attribute(x, span))
 }
 
 /// namespace_decl = namespace ident ( . ident )* ;
 fn namespace<I>() -> impl Parser<Input = I, Output = Stmt>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    string("namespace")
-        .skip(spaces())
+    position()
+        .skip(keyword("namespace"))
+        .skip(blank())
         .and(sep_by1::<Vec<Identifier>, _, _>(identifier(), token('.')))
-        .skip(spaces())
+        .skip(blank())
         .skip(token(';'))
-        .skip(spaces())
-        .map(|(_, id)| Stmt::Namespace(id))
+        .skip(blank())
+        .map(|(span, id)| Stmt::Namespace(id, span))
 }
 
 /// file_identifier_decl = file_identifier string_constant ;
 fn file_identifier<I>() -> impl Parser<Input = I, Output = Stmt>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    position()
+        .skip(keyword("file_identifier"))
+        .skip(blank())
+        .and(string_constant())
+        .skip(blank())
+        .skip(token(';'))
+        .skip(blank())
+        .and_then(|(span, id)| {
+            if id.len() == 4 {
+                Ok(Stmt::FileIdentifier(id, span))
+            } else {
+                Err(StreamErrorFor::<I>::message_static_message(
+                    "file_identifier must be exactly 4 bytes",
+                ))
+            }
+        })
+}
+
+/// file_extension_decl = file_extension string_constant ;
+fn file_extension<I>() -> impl Parser<Input = I, Output = Stmt>
+where
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    string("file_identifier")
-        .skip(spaces())
-        .and(quoted(identifier()))
-        .skip(spaces())
+    position()
+        .skip(keyword("file_extension"))
+        .skip(blank())
+        .and(string_constant())
+        .skip(blank())
         .skip(token(';'))
-        .skip(spaces())
-        .map(|(_, id)| Stmt::FileIdentifier(id))
+        .skip(blank())
+        .map(|(span, ext)| Stmt::FileExtension(ext, span))
 }
 
-/// root_decl = root_type ident ;
+/// root_decl = root_type ident ( . ident )* ;
 fn root<I>() -> impl Parser<Input = I, Output = Stmt>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    string("root_type")
-        .skip(spaces())
-        .and(identifier())
-        .skip(spaces())
+    position()
+        .skip(keyword("root_type"))
+        .skip(blank())
+        .and(qualified_identifier())
+        .skip(blank())
         .skip(token(';'))
-        .skip(spaces())
-        .map(|(_, id)| Stmt::Root(id))
+        .skip(blank())
+        .map(|(span, id)| Stmt::Root(id, span))
 }
 
+/// table_decl = table ident metadata { field_decl* }
 fn table<I>() -> impl Parser<Input = I, Output = Stmt>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    string("table")
-        .skip(spaces())
+    position()
+        .skip(keyword("table"))
+        .skip(blank())
         .and(identifier())
-        .skip(spaces())
-        .and(paren(many1(field())))
-        .skip(spaces())
-        .map(|((_, id), fields)| Stmt::Table(Table { id, fields }))
+        .skip(blank())
+        .and(optional(metadata()))
+        .skip(blank())
+        .and(paren(many(field())))
+        .skip(blank())
+        .map(|(((span, id), metadata), fields)| {
+            Stmt::Table(Table {
+                id,
+                fields,
+                metadata,
+                doc: Vec::new(),
+                span,
+            })
+        })
+}
+
+/// Field of struct, which cannot have default value nor non-scalar type
+fn struct_field<I>() -> impl Parser<Input = I, Output = Field>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    field().and_then(|field| {
+        if field.default.is_some() {
+            return Err(StreamErrorFor::<I>::message_static_message(
+                "struct field cannot have default value",
+            ));
+        }
+        let inline = match &field.ty {
+            Type::String_ | Type::Vector(_) => false,
+            Type::Array(ty, _) => {
+                !matches!(**ty, Type::String_ | Type::Vector(_) | Type::Array(..))
+            }
+            _ => true,
+        };
+        if inline {
+            Ok(field)
+        } else {
+            Err(StreamErrorFor::<I>::message_static_message(
+                "struct field must be scalar or struct",
+            ))
+        }
+    })
 }
 
-/// enum_decl = ( enum ident [ : type ] | union ident ) metadata { commasep( enumval_decl ) }
+/// struct_decl = struct ident metadata { field_decl+ }
+fn struct_<I>() -> impl Parser<Input = I, Output = Stmt>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    position()
+        .skip(keyword("struct"))
+        .skip(blank())
+        .and(identifier())
+        .skip(blank())
+        .and(optional(metadata()))
+        .skip(blank())
+        .and(paren(many1(struct_field())))
+        .skip(blank())
+        .map(|(((span, id), metadata), fields)| {
+            Stmt::Struct(Struct {
+                id,
+                fields,
+                metadata,
+                doc: Vec::new(),
+                span,
+            })
+        })
+}
+
+/// enum_decl = enum ident [ : type ] metadata { commasep( enumval_decl ) [ , ] }
 fn enum_<I>() -> impl Parser<Input = I, Output = Stmt>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    position()
+        .skip(keyword("enum"))
+        .skip(blank())
+        .and(identifier())
+        .skip(blank())
+        .and(optional(token(':').skip(blank()).and(ty()).map(|x| x.1)))
+        .skip(blank())
+        .and(optional(metadata()))
+        .skip(blank())
+        .and(paren(sep_end_by1(enumval(), token(',').skip(blank()))))
+        .skip(blank())
+        .map(|((((span, id), ty), metadata), values)| {
+            Stmt::Enum(Enum {
+                id,
+                ty,
+                values,
+                metadata,
+                doc: Vec::new(),
+                span,
+            })
+        })
+}
+
+/// union_decl = union ident metadata { commasep( unionval_decl ) [ , ] }
+fn union<I>() -> impl Parser<Input = I, Output = Stmt>
+where
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    string("enum")
-        .skip(spaces())
+    position()
+        .skip(keyword("union"))
+        .skip(blank())
         .and(identifier())
-        .skip(spaces())
-        .and(optional(token(':').skip(spaces()).and(ty()).map(|x| x.1)))
-        .skip(spaces())
-        .and(paren(sep_by1(enumval(), token(',').skip(spaces()))))
-        .skip(spaces())
-        .map(|(((_, id), ty), values)| Stmt::Enum(Enum { id, ty, values }))
+        .skip(blank())
+        .and(optional(metadata()))
+        .skip(blank())
+        .and(paren(sep_end_by1(unionval(), token(',').skip(blank()))))
+        .skip(blank())
+        .map(|(((span, id), metadata), values)| {
+            Stmt::Union(Union {
+                id,
+                values,
+                metadata,
+                doc: Vec::new(),
+                span,
+            })
+        })
+}
+
+/// rpc_decl = rpc_service ident { rpc_method+ }
+fn rpc_service<I>() -> impl Parser<Input = I, Output = Stmt>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    position()
+        .skip(keyword("rpc_service"))
+        .skip(blank())
+        .and(identifier())
+        .skip(blank())
+        .and(paren(many1(rpc_method())))
+        .skip(blank())
+        .map(|((span, id), methods)| {
+            Stmt::RpcService(RpcService {
+                id,
+                methods,
+                doc: Vec::new(),
+                span,
+            })
+        })
+}
+
+/// Attach doc comments to the declaration, or drop them for other statements
+fn with_doc(doc: Vec<String>, mut stmt: Stmt) -> Stmt {
+    match &mut stmt {
+        Stmt::Table(Table { doc: d, .. })
+        | Stmt::Struct(Struct { doc: d, .. })
+        | Stmt::Enum(Enum { doc: d, .. })
+        | Stmt::Union(Union { doc: d, .. })
+        | Stmt::RpcService(RpcService { doc: d, .. }) => *d = doc,
+        _ => {}
+    }
+    stmt
+}
+
+/// Statement with the preceding doc comments
+fn stmt<I>() -> impl Parser<Input = I, Output = Stmt>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    let stmt = choice((
+        include(),
+        attribute(),
+        table(),
+        struct_(),
+        enum_(),
+        union(),
+        namespace(),
+        root(),
+        rpc_service(),
+        file_identifier(),
+        file_extension(),
+    ));
+    doc_comment()
+        .and(stmt)
+        .map(|(doc, stmt)| with_doc(doc, stmt))
+}
+
+/// Parse a schema file, skipping broken statements to report all syntax errors
+///
+/// Returns statements parsed successfully and errors in order of appearance.
+pub fn fbs_recover(input: State<&str, Span>) -> (Vec<Stmt>, Vec<easy::Errors<char, &str, Span>>) {
+    let mut stmt_list = Vec::new();
+    let mut errors = Vec::new();
+    let mut input = match blank().easy_parse(input) {
+        Ok(((), rest)) => rest,
+        Err(err) => return (stmt_list, vec![err]),
+    };
+    while blank_all().with(eof()).parse(input.clone()).is_err() {
+        match stmt().easy_parse(input.clone()) {
+            Ok((st, rest)) => {
+                stmt_list.push(st);
+                input = rest;
+            }
+            Err(err) => {
+                errors.push(err);
+                match recover().parse(input) {
+                    Ok(((), rest)) => input = rest,
+                    Err(_) => break,
+                }
+            }
+        }
+    }
+    (stmt_list, errors)
+}
+
+/// String constant or comment, which may contain `;` and braces
+///
+/// Unterminated ones are skipped until the end of input.
+fn skip_text<I>() -> impl Parser<Input = I, Output = ()>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    let quoted = token('"')
+        .with(skip_many(
+            token('\\')
+                .with(any())
+                .or(satisfy(|c| c != '"' && c != '\\')),
+        ))
+        .skip(optional(token('"')));
+    let line = attempt(string("//")).with(skip_many(satisfy(|c| c != '\n')));
+    let block = attempt(string("/*"))
+        .with(skip_many(
+            satisfy(|c| c != '*').or(attempt(token('*').skip(not_followed_by(token('/'))))),
+        ))
+        .skip(optional(string("*/")));
+    choice((quoted, line, block))
+}
+
+/// `{ ... }` with nested blocks
+fn skip_block<I>() -> impl Parser<Input = I, Output = ()>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    token('{')
+        .with(skip_many(choice((
+            skip_text(),
+            parser(|input: &mut I| skip_block().parse_stream(input)),
+            satisfy(|c| c != '{' && c != '}').map(|_| ()),
+        ))))
+        .skip(optional(token('}')))
+}
+
+/// Skip the rest of a broken statement to resynchronize after a syntax error
+///
+/// Input is skipped through the next `;` or block `{ ... }` at the top level,
+/// or a stray `}`.
+fn recover<I>() -> impl Parser<Input = I, Output = ()>
+where
+    I: Stream<Item = char, Position = Span>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    skip_many(choice((
+        skip_text(),
+        satisfy(|c| c != ';' && c != '{' && c != '}').map(|_| ()),
+    )))
+    .skip(optional(choice((
+        token(';').map(|_| ()),
+        skip_block(),
+        token('}').map(|_| ()),
+    ))))
+    .skip(blank())
 }
 
 /// Entry point of schema language
 pub fn fbs<I>() -> impl Parser<Input = I, Output = Vec<Stmt>>
 where
-    I: Stream<Item = char>,
+    I: Stream<Item = char, Position = Span>,
     I::Error: ParseError<I::Item, I::Range, I::Position>,
 {
-    spaces() // Drop head spaces
-        .and(many(choice((
-            table(),
-            enum_(),
-            namespace(),
-            root(),
-            file_identifier(),
-        ))))
-        .map(|x| x.1)
+    blank() // Drop head spaces
+        .with(many(stmt()))
+        .skip(blank_all())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use combine::{error::StringStreamError, stream::state::State};
+
+    /// Positioner ignoring positions, so that all spans are `Span::default()`
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+    struct NoPosition;
+
+    impl Positioner<char> for NoPosition {
+        type Position = Span;
+
+        fn position(&self) -> Span {
+            Span::default()
+        }
+
+        fn update(&mut self, _: &char) {}
+    }
+
+    impl Resetable for NoPosition {
+        type Checkpoint = Self;
+
+        fn checkpoint(&self) -> Self {
+            NoPosition
+        }
+
+        fn reset(&mut self, _: Self) {}
+    }
+
+    /// Parse with normalizing spans to compare AST, and returns the rest of input
+    fn parse<'a, P>(mut p: P, input: &'a str) -> Result<(P::Output, &'a str), StringStreamError>
+    where
+        P: Parser<Input = State<&'a str, NoPosition>>,
+    {
+        p.parse(State::with_positioner(input, NoPosition))
+            .map(|(out, rest)| (out, rest.input))
+    }
 
     #[test]
     fn test_identifier() {
         assert_eq!(
-            identifier().parse("id_id_").unwrap(),
+            parse(identifier(), "id_id_").unwrap(),
             ("id_id_".to_string(), "")
         );
     }
 
     #[test]
     fn test_integer() {
-        assert_eq!(integer().parse("1234"), Ok((1234, "")));
-        assert_eq!(integer().parse("-1234"), Ok((-1234, "")));
-        assert_eq!(integer().parse("- 1234"), Ok((-1234, "")));
+        assert_eq!(parse(integer(), "1234"), Ok((1234, "")));
+        assert_eq!(parse(integer(), "-1234"), Ok((-1234, "")));
+        assert_eq!(parse(integer(), "- 1234"), Ok((-1234, "")));
+        assert_eq!(parse(integer(), "+5"), Ok((5, "")));
+        assert_eq!(parse(integer(), "0xFF"), Ok((255, "")));
+        assert_eq!(parse(integer(), "-0X10"), Ok((-16, "")));
+        assert_eq!(
+            parse(integer(), "18446744073709551615"),
+            Ok((u64::MAX.into(), ""))
+        );
+        assert_eq!(
+            parse(integer(), "-9223372036854775808"),
+            Ok((i64::MIN.into(), ""))
+        );
+        assert!(parse(integer(), "18446744073709551616").is_err());
+        assert!(parse(integer(), "-9223372036854775809").is_err());
+        assert!(parse(integer(), "1.5").is_err());
+    }
+
+    #[test]
+    fn test_scalar() {
+        assert_eq!(parse(scalar(), "1e10"), Ok((Scalar::Float(1e10), "")));
+        assert_eq!(parse(scalar(), "-1.5E-3"), Ok((Scalar::Float(-1.5e-3), "")));
+        assert_eq!(parse(scalar(), ".5"), Ok((Scalar::Float(0.5), "")));
+        assert_eq!(parse(scalar(), "3."), Ok((Scalar::Float(3.0), "")));
+        assert_eq!(parse(scalar(), "0x1.8p1"), Ok((Scalar::Float(3.0), "")));
+        assert_eq!(parse(scalar(), "0x10p-4"), Ok((Scalar::Float(1.0), "")));
+        assert!(parse(scalar(), "0x1.8").is_err());
+        assert_eq!(parse(scalar(), "true"), Ok((Scalar::Bool(true), "")));
+        assert_eq!(parse(scalar(), "false"), Ok((Scalar::Bool(false), "")));
+        assert_eq!(
+            parse(scalar(), "-inf"),
+            Ok((Scalar::Float(f64::NEG_INFINITY), ""))
+        );
+        assert_eq!(
+            parse(scalar(), "infinity"),
+            Ok((Scalar::Float(f64::INFINITY), ""))
+        );
+        match parse(scalar(), "+nan") {
+            Ok((Scalar::Float(x), "")) => assert!(x.is_nan()),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        assert_eq!(
+            parse(scalar(), "Banana"),
+            Ok((Scalar::Identifier("Banana".into()), ""))
+        );
+        assert!(parse(scalar(), "-Banana").is_err());
+        assert_eq!(parse(scalar(), "null"), Ok((Scalar::Null, "")));
+        assert!(parse(scalar(), "-null").is_err());
+    }
+
+    #[test]
+    fn test_string_constant() {
+        assert_eq!(
+            parse(string_constant(), r#""http://example.com""#),
+            Ok(("http://example.com".to_string(), ""))
+        );
+        assert_eq!(
+            parse(string_constant(), r#""a\n\"b\\\/\t""#),
+            Ok(("a\n\"b\\/\t".to_string(), ""))
+        );
+        assert_eq!(
+            parse(string_constant(), r#""\x41\u1234""#),
+            Ok(("A\u{1234}".to_string(), ""))
+        );
+        assert_eq!(
+            parse(string_constant(), r#""\uD83D\uDE00""#),
+            Ok(("\u{1F600}".to_string(), ""))
+        );
+        assert!(parse(string_constant(), r#""\uD83D""#).is_err());
+        assert!(parse(string_constant(), r#""\q""#).is_err());
     }
 
     #[test]
     fn test_type() {
-        assert_eq!(ty().parse("bool").unwrap(), (Type::Bool, ""));
-        assert_eq!(ty().parse("long").unwrap(), (Type::Int64, ""));
+        assert_eq!(parse(ty(), "bool").unwrap(), (Type::Bool, ""));
+        assert_eq!(parse(ty(), "long").unwrap(), (Type::Int64, ""));
+        assert_eq!(
+            parse(ty(), "[string]").unwrap(),
+            (Type::Vector(Box::new(Type::String_)), "")
+        );
+        assert_eq!(
+            parse(ty(), "[float:16]").unwrap(),
+            (Type::Array(Box::new(Type::Float32), 16), "")
+        );
+        assert_eq!(
+            parse(ty(), "[Geometry.Vec3:2]").unwrap(),
+            (
+                Type::Array(Box::new(Type::UserDefined("Geometry.Vec3".into())), 2),
+                ""
+            )
+        );
+        assert!(parse(ty(), "Geometry.").is_err());
+        assert_eq!(
+            parse(ty(), "[int:0xF]").unwrap(),
+            (Type::Array(Box::new(Type::Int32), 15), "")
+        );
+        assert!(parse(ty(), "[float:0]").is_err());
+        assert!(parse(ty(), "[float:65536]").is_err());
+        // Nested vectors are parsed, and rejected when resolved in schema
+        assert_eq!(
+            parse(ty(), "[ [Monster] ]").unwrap(),
+            (
+                Type::Vector(Box::new(Type::Vector(Box::new(Type::UserDefined(
+                    "Monster".into()
+                ))))),
+                ""
+            )
+        );
     }
 
     #[test]
     fn test_metadata() {
         assert_eq!(
-            metadata().parse("( deprecated)"),
-            Ok((vec!["deprecated".into()], ""))
+            parse(metadata(), "( deprecated)"),
+            Ok((vec![("deprecated".into(), None)], ""))
         );
         assert_eq!(
-            metadata().parse("( deprecated )"),
-            Ok((vec!["deprecated".into()], ""))
+            parse(metadata(), "( deprecated )"),
+            Ok((vec![("deprecated".into(), None)], ""))
+        );
+        assert_eq!(
+            parse(
+                metadata(),
+                r#"(id: 3, key, force_align: 16, hash: "fnv1_32", priority)"#
+            ),
+            Ok((
+                vec![
+                    ("id".into(), Some(Value::Scalar(Scalar::Integer(3)))),
+                    ("key".into(), None),
+                    (
+                        "force_align".into(),
+                        Some(Value::Scalar(Scalar::Integer(16)))
+                    ),
+                    ("hash".into(), Some(Value::String("fnv1_32".into()))),
+                    ("priority".into(), None),
+                ],
+                ""
+            ))
         );
     }
 
     #[test]
     fn test_field() {
         assert_eq!(
-            field().parse("a : uint32;"),
+            parse(field(), "a : uint32;"),
             Ok((
                 Field {
                     id: "a".into(),
                     ty: Type::UInt32,
-                    scalar: None,
-                    metadata: None
+                    default: None,
+                    metadata: None,
+                    doc: vec![],
+                    span: Span::default(),
                 },
                 ""
             ))
         );
 
         assert_eq!(
-            field().parse("a : uint32 = 1;"),
+            parse(field(), "a : uint32 = 1;"),
             Ok((
                 Field {
                     id: "a".into(),
                     ty: Type::UInt32,
-                    scalar: Some(Scalar::Integer(1)),
-                    metadata: None
+                    default: Some(Value::Scalar(Scalar::Integer(1))),
+                    metadata: None,
+                    doc: vec![],
+                    span: Span::default(),
                 },
                 ""
             ))
         );
 
         assert_eq!(
-            field().parse("a : Fruit = Banana;"),
+            parse(field(), "a : Fruit = Banana;"),
             Ok((
                 Field {
                     id: "a".into(),
                     ty: Type::UserDefined("Fruit".into()),
-                    scalar: Some(Scalar::Identifier("Banana".into())),
-                    metadata: None
+                    default: Some(Value::Scalar(Scalar::Identifier("Banana".into()))),
+                    metadata: None,
+                    doc: vec![],
+                    span: Span::default(),
                 },
                 ""
             ))
         );
+
+        assert_eq!(
+            parse(field(), "hp: short = null;").map(|(f, _)| f.default),
+            Ok(Some(Value::Scalar(Scalar::Null)))
+        );
+        assert_eq!(
+            parse(field(), r#"c: Color = "Red Blue";"#).map(|(f, _)| f.default),
+            Ok(Some(Value::String("Red Blue".into())))
+        );
+        assert!(parse(struct_field(), "hp: short = null;").is_err());
     }
 
     #[test]
     fn test_enumval() {
         assert_eq!(
-            enumval().parse("Banana"),
+            parse(enumval(), "Banana"),
             Ok((
                 EnumVal {
                     id: "Banana".into(),
                     integer_constant: None,
+                    doc: vec![],
+                    span: Span::default(),
                 },
                 ""
             ))
         );
         assert_eq!(
-            enumval().parse("Banana = -1"),
+            parse(enumval(), "Banana = -1"),
             Ok((
                 EnumVal {
                     id: "Banana".into(),
                     integer_constant: Some(-1),
+                    doc: vec![],
+                    span: Span::default(),
                 },
                 ""
             ))
@@ -438,7 +1511,7 @@ mod tests {
     #[test]
     fn test_enum() {
         assert_eq!(
-            enum_().parse("enum Fruit { Banana = -1, Orange = 42 }"),
+            parse(enum_(), "enum Fruit { Banana = -1, Orange = 42 }"),
             Ok((
                 Stmt::Enum(Enum {
                     id: "Fruit".into(),
@@ -446,19 +1519,26 @@ mod tests {
                     values: vec![
                         EnumVal {
                             id: "Banana".into(),
-                            integer_constant: Some(-1)
+                            integer_constant: Some(-1),
+                            doc: vec![],
+                            span: Span::default(),
                         },
                         EnumVal {
                             id: "Orange".into(),
-                            integer_constant: Some(42)
+                            integer_constant: Some(42),
+                            doc: vec![],
+                            span: Span::default(),
                         },
                     ],
+                    metadata: None,
+                    doc: vec![],
+                    span: Span::default(),
                 }),
                 ""
             ))
         );
         assert_eq!(
-            enum_().parse("enum Fruit : byte { Banana = -1, Orange = 42 }"),
+            parse(enum_(), "enum Fruit : byte { Banana = -1, Orange = 42 }"),
             Ok((
                 Stmt::Enum(Enum {
                     id: "Fruit".into(),
@@ -466,54 +1546,144 @@ mod tests {
                     values: vec![
                         EnumVal {
                             id: "Banana".into(),
-                            integer_constant: Some(-1)
+                            integer_constant: Some(-1),
+                            doc: vec![],
+                            span: Span::default(),
                         },
                         EnumVal {
                             id: "Orange".into(),
-                            integer_constant: Some(42)
+                            integer_constant: Some(42),
+                            doc: vec![],
+                            span: Span::default(),
                         },
                     ],
+                    metadata: None,
+                    doc: vec![],
+                    span: Span::default(),
                 }),
                 ""
             ))
         );
     }
 
+    #[test]
+    fn test_include() {
+        assert_eq!(
+            parse(include(), r#"include "sub/other.fbs";"#),
+            Ok((Stmt::Include("sub/other.fbs".into(), Span::default()), ""))
+        );
+    }
+
+    #[test]
+    fn test_attribute() {
+        assert_eq!(
+            parse(attribute(), r#"attribute "priority";"#),
+            Ok((Stmt::Attribute("priority".into(), Span::default()), ""))
+        );
+        assert_eq!(
+            parse(attribute(), "attribute priority;"),
+            Ok((Stmt::Attribute("priority".into(), Span::default()), ""))
+        );
+    }
+
     #[test]
     fn test_namespace() {
         assert_eq!(
-            namespace().parse("namespace mad.magi;").unwrap(),
-            (Stmt::Namespace(vec!["mad".into(), "magi".into()]), "")
+            parse(namespace(), "namespace mad.magi;").unwrap(),
+            (
+                Stmt::Namespace(vec!["mad".into(), "magi".into()], Span::default()),
+                ""
+            )
         );
     }
 
     #[test]
     fn test_file_identifier() {
         assert_eq!(
-            file_identifier().parse(r#"file_identifier "NOOB";"#),
-            Ok((Stmt::FileIdentifier("NOOB".into()), ""))
+            parse(file_identifier(), r#"file_identifier "NOOB";"#),
+            Ok((Stmt::FileIdentifier("NOOB".into(), Span::default()), ""))
+        );
+        assert_eq!(
+            parse(file_identifier(), r#"file_identifier "0ABC";"#),
+            Ok((Stmt::FileIdentifier("0ABC".into(), Span::default()), ""))
+        );
+        assert_eq!(
+            parse(file_identifier(), r#"file_identifier "A-B_";"#),
+            Ok((Stmt::FileIdentifier("A-B_".into(), Span::default()), ""))
+        );
+        assert!(parse(file_identifier(), r#"file_identifier "ABCDE";"#).is_err());
+    }
+
+    #[test]
+    fn test_file_extension() {
+        assert_eq!(
+            parse(file_extension(), r#"file_extension "mon";"#),
+            Ok((Stmt::FileExtension("mon".into(), Span::default()), ""))
+        );
+        assert!(parse(file_extension(), "file_extension mon;").is_err());
+        assert_eq!(
+            parse(fbs(), r#"file_identifier "MONS"; file_extension "mon";"#)
+                .unwrap()
+                .0
+                .len(),
+            2
         );
     }
 
     #[test]
     fn test_root() {
         assert_eq!(
-            root().parse("root_type A;").unwrap(),
-            (Stmt::Root("A".into()), "")
+            parse(root(), "root_type A;").unwrap(),
+            (Stmt::Root("A".into(), Span::default()), "")
+        );
+    }
+
+    #[test]
+    fn test_trailing_comma() {
+        match parse(
+            enum_(),
+            "enum Race : byte { None = -1, Human = 0, Dwarf, Elf, }",
+        ) {
+            Ok((Stmt::Enum(e), "")) => assert_eq!(e.values.len(), 4),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        match parse(union(), "union Gadget { FallingTub, HandFan, }") {
+            Ok((Stmt::Union(u), "")) => assert_eq!(u.values.len(), 2),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        assert!(parse(enum_(), "enum E : byte { , }").is_err());
+        assert!(parse(enum_(), "enum E : byte { A,, }").is_err());
+    }
+
+    #[test]
+    fn test_empty_table() {
+        assert_eq!(
+            parse(table(), "table InParentNamespace {}"),
+            Ok((
+                Stmt::Table(Table {
+                    id: "InParentNamespace".into(),
+                    fields: vec![],
+                    metadata: None,
+                    doc: vec![],
+                    span: Span::default(),
+                }),
+                ""
+            ))
         );
+        assert!(parse(struct_(), "struct Empty {}").is_err());
     }
 
     #[test]
     fn test_table() {
         assert_eq!(
-            table()
-                .parse(
-                    r#"table A {
+            parse(
+                table(),
+                r#"table A {
                         a: int32;
                         b: int32;
                     }"#,
-                )
-                .unwrap(),
+            )
+            .unwrap(),
             (
                 Stmt::Table(Table {
                     id: "A".to_string(),
@@ -521,19 +1691,320 @@ mod tests {
                         Field {
                             id: "a".into(),
                             ty: Type::Int32,
-                            scalar: None,
-                            metadata: None
+                            default: None,
+                            metadata: None,
+                            doc: vec![],
+                            span: Span::default(),
                         },
                         Field {
                             id: "b".into(),
                             ty: Type::Int32,
-                            scalar: None,
-                            metadata: None
+                            default: None,
+                            metadata: None,
+                            doc: vec![],
+                            span: Span::default(),
                         }
-                    ]
+                    ],
+                    metadata: None,
+                    doc: vec![],
+                    span: Span::default(),
                 }),
                 ""
             )
         );
     }
+
+    #[test]
+    fn test_struct() {
+        assert_eq!(
+            parse(struct_(), "struct Vec3 { x: float; y: float; z: float; }").unwrap(),
+            (
+                Stmt::Struct(Struct {
+                    id: "Vec3".to_string(),
+                    fields: ["x", "y", "z"]
+                        .iter()
+                        .map(|id| Field {
+                            id: id.to_string(),
+                            ty: Type::Float32,
+                            default: None,
+                            metadata: None,
+                            doc: vec![],
+                            span: Span::default(),
+                        })
+                        .collect(),
+                    metadata: None,
+                    doc: vec![],
+                    span: Span::default(),
+                }),
+                ""
+            )
+        );
+        assert!(parse(struct_(), "struct A { a: int = 1; }").is_err());
+        assert!(parse(struct_(), "struct A { a: string; }").is_err());
+        assert!(parse(struct_(), "struct A { a: [int]; }").is_err());
+        assert!(parse(struct_(), "struct A { a: [int:4]; }").is_ok());
+        assert!(parse(struct_(), "struct A { a: [string:4]; }").is_err());
+    }
+
+    #[test]
+    fn test_qualified_reference() {
+        assert_eq!(
+            parse(root(), "root_type Game.Monster;"),
+            Ok((Stmt::Root("Game.Monster".into(), Span::default()), ""))
+        );
+        match parse(union(), "union Any { Game.Monster, Pet: Zoo.Animal }") {
+            Ok((Stmt::Union(u), "")) => {
+                assert_eq!(u.values[0].ty, "Game.Monster");
+                assert_eq!(u.values[1].ty, "Zoo.Animal");
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        assert!(parse(union(), "union Any { Zoo.Pet: Animal }").is_err());
+        match parse(rpc_service(), "rpc_service S { M(A.Req):B.Res; }") {
+            Ok((Stmt::RpcService(s), "")) => {
+                assert_eq!(s.methods[0].request, "A.Req");
+                assert_eq!(s.methods[0].response, "B.Res");
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_union() {
+        assert_eq!(
+            parse(union(), "union Any { Monster, Pet: Animal }"),
+            Ok((
+                Stmt::Union(Union {
+                    id: "Any".into(),
+                    values: vec![
+                        UnionVal {
+                            alias: None,
+                            ty: "Monster".into(),
+                            doc: vec![],
+                            span: Span::default(),
+                        },
+                        UnionVal {
+                            alias: Some("Pet".into()),
+                            ty: "Animal".into(),
+                            doc: vec![],
+                            span: Span::default(),
+                        },
+                    ],
+                    metadata: None,
+                    doc: vec![],
+                    span: Span::default(),
+                }),
+                ""
+            ))
+        );
+    }
+
+    #[test]
+    fn test_rpc_service() {
+        assert_eq!(
+            parse(
+                rpc_service(),
+                r#"rpc_service Greeter {
+                    SayHello(HelloRequest):HelloReply (streaming: "server");
+                    SayManyHellos (ManyHellosRequest) : HelloReply;
+                }"#
+            ),
+            Ok((
+                Stmt::RpcService(RpcService {
+                    id: "Greeter".into(),
+                    methods: vec![
+                        RpcMethod {
+                            id: "SayHello".into(),
+                            request: "HelloRequest".into(),
+                            response: "HelloReply".into(),
+                            metadata: Some(vec![(
+                                "streaming".into(),
+                                Some(Value::String("server".into()))
+                            )]),
+                            doc: vec![],
+                            span: Span::default(),
+                        },
+                        RpcMethod {
+                            id: "SayManyHellos".into(),
+                            request: "ManyHellosRequest".into(),
+                            response: "HelloReply".into(),
+                            metadata: None,
+                            doc: vec![],
+                            span: Span::default(),
+                        },
+                    ],
+                    doc: vec![],
+                    span: Span::default(),
+                }),
+                ""
+            ))
+        );
+    }
+
+    #[test]
+    fn test_keyword() {
+        assert!(parse(fbs(), "tables").is_ok());
+        assert_eq!(parse(fbs(), "tables").unwrap().1, "tables");
+        assert_eq!(
+            parse(fbs(), "root_type A; rpc_service S { M(A):A; }")
+                .unwrap()
+                .1,
+            ""
+        );
+    }
+
+    #[test]
+    fn test_comment() {
+        assert_eq!(
+            parse(blank(), "  // comment\n  /* block\n * comment */ a"),
+            Ok(((), "a"))
+        );
+        assert_eq!(parse(blank(), "/// doc"), Ok(((), "/// doc")));
+        assert_eq!(parse(blank_all(), "// a\n/// doc\n/**/"), Ok(((), "")));
+        // comment without final newline
+        assert_eq!(
+            parse(fbs(), "root_type A; // end"),
+            Ok((vec![Stmt::Root("A".into(), Span::default())], ""))
+        );
+        // `//` in string literal is not a comment
+        assert_eq!(
+            parse(fbs(), r#"attribute "http://example.com"; /* trailing */"#),
+            Ok((
+                vec![Stmt::Attribute(
+                    "http://example.com".into(),
+                    Span::default()
+                )],
+                ""
+            ))
+        );
+    }
+
+    #[test]
+    fn test_doc_comment() {
+        let (stmt, res) = parse(
+            fbs(),
+            r#"
+                /// A fruit
+                enum Fruit : byte {
+                    /// Yellow one
+                    Banana,
+                    Orange /// dangling
+                }
+                /// Table doc
+                ///  second line
+                table A {
+                    // not a doc
+                    /// Field doc
+                    a: Fruit;
+                    /// dangling
+                }
+                /// dangling at EOF
+                "#,
+        )
+        .unwrap();
+        assert_eq!(res, "");
+        match &stmt[..] {
+            [Stmt::Enum(e), Stmt::Table(t)] => {
+                assert_eq!(e.doc, vec![" A fruit"]);
+                assert_eq!(e.values[0].doc, vec![" Yellow one"]);
+                assert!(e.values[1].doc.is_empty());
+                assert_eq!(t.doc, vec![" Table doc", "  second line"]);
+                assert_eq!(t.fields[0].doc, vec![" Field doc"]);
+            }
+            _ => panic!("Unexpected statements: {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_span() {
+        let input = State::with_positioner(
+            "namespace A;\n\n/// doc\ntable T {\n  a: int;\n    b: [T];\n}\n",
+            Span::new("a.fbs"),
+        );
+        let (stmt, _) = fbs().parse(input).unwrap();
+        assert_eq!(stmt[0].span().to_string(), "a.fbs:1:1");
+        assert_eq!(stmt[1].span().to_string(), "a.fbs:4:1");
+        match &stmt[1] {
+            Stmt::Table(table) => {
+                assert_eq!(table.fields[0].span.to_string(), "a.fbs:5:3");
+                assert_eq!(table.fields[1].span.to_string(), "a.fbs:6:5");
+            }
+            _ => panic!("Unexpected statement: {:?}", stmt[1]),
+        }
+    }
+
+    #[test]
+    fn test_recover() {
+        assert_eq!(
+            parse(recover(), "a: int b; c: int; }"),
+            Ok(((), "c: int; }"))
+        );
+        assert_eq!(
+            parse(
+                recover(),
+                "table T { a: int b; s: string = \"}\"; } root_type T;"
+            ),
+            Ok(((), "root_type T;"))
+        );
+        assert_eq!(
+            parse(recover(), "T { /* } */ a: [int; { } }\n// ;\nb"),
+            Ok(((), "b"))
+        );
+        assert_eq!(parse(recover(), "} table"), Ok(((), "table")));
+        assert_eq!(parse(recover(), "table T { a: int;"), Ok(((), "")));
+    }
+
+    #[test]
+    fn test_fbs_recover() {
+        let input = State::with_positioner(
+            "namespace A;\ntable T { a: int b: int; c: int; }\nstruct S { x: float; }\nroot_type;\nenum E : byte { X }\n",
+            Span::new("a.fbs"),
+        );
+        let (stmt, errors) = fbs_recover(input);
+        let spans: Vec<_> = errors.iter().map(|e| e.position.to_string()).collect();
+        assert_eq!(spans, ["a.fbs:2:18", "a.fbs:4:10"]);
+        match &stmt[..] {
+            [Stmt::Namespace(..), Stmt::Struct(s), Stmt::Enum(e)] => {
+                assert_eq!(s.id, "S");
+                assert_eq!(e.id, "E");
+            }
+            _ => panic!("Unexpected statements: {:?}", stmt),
+        }
+        let (stmt, errors) = fbs_recover(State::with_positioner("", Span::default()));
+        assert!(stmt.is_empty() && errors.is_empty());
+        let (_, errors) = fbs_recover(State::with_positioner("table T { /* ", Span::default()));
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_type_metadata() {
+        let (stmt, rest) = parse(
+            fbs(),
+            r#"
+            table Monster (csharp_partial) { hp: short; }
+            struct Vec3 (force_align: 16) { x: float; }
+            enum Color : ubyte (bit_flags) { Red, Green }
+            union Any (priority: 1) { Monster }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        let flag = |name: &str| Some(vec![(name.to_string(), None)]);
+        match &stmt[..] {
+            [Stmt::Table(t), Stmt::Struct(s), Stmt::Enum(e), Stmt::Union(u)] => {
+                assert_eq!(t.metadata, flag("csharp_partial"));
+                assert_eq!(
+                    s.metadata,
+                    Some(vec![(
+                        "force_align".to_string(),
+                        Some(Value::Scalar(Scalar::Integer(16)))
+                    )])
+                );
+                assert_eq!(e.metadata, flag("bit_flags"));
+                assert_eq!(e.ty, Some(Type::UInt8));
+                assert!(u.metadata.is_some());
+            }
+            _ => panic!("Unexpected statements: {:?}", stmt),
+        }
+    }
 }
@@ -0,0 +1,981 @@
+//! Schema model where references to user-defined types are resolved
+
+use crate::{
+    layout::{struct_layouts, StructLayout},
+    parser::*,
+    semantics::{
+        check_field_ids, enum_values, force_align, integer_range, qualify, seek_file_extension,
+        seek_file_identifier, seek_root_type, with_namespace, Kind,
+    },
+};
+use failure::*;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Handle of a table, i.e. index of [`Schema::tables`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TableId(pub usize);
+
+/// Handle of a struct, i.e. index of [`Schema::structs`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StructId(pub usize);
+
+/// Handle of an enum, i.e. index of [`Schema::enums`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EnumId(pub usize);
+
+/// Handle of a union, i.e. index of [`Schema::unions`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UnionId(pub usize);
+
+/// User-defined type
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Symbol {
+    Table(TableId),
+    Struct(StructId),
+    Enum(EnumId),
+    Union(UnionId),
+}
+
+impl Symbol {
+    pub fn kind(self) -> Kind {
+        match self {
+            Symbol::Table(_) => Kind::Table,
+            Symbol::Struct(_) => Kind::Struct,
+            Symbol::Enum(_) => Kind::Enum,
+            Symbol::Union(_) => Kind::Union,
+        }
+    }
+}
+
+/// Type where user-defined types are resolved into handles
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeRef {
+    /// Scalars and string, i.e. [`Type`] except vectors, arrays and user-defined types
+    Builtin(Type),
+    Vector(Box<TypeRef>),
+    Array(Box<TypeRef>, u16),
+    Defined(Symbol),
+}
+
+/// How a value is stored in the table or struct containing it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Storage {
+    /// Inline scalar including enum
+    Scalar,
+    /// Inline struct
+    Struct,
+    /// Offset to the value out of line, e.g. string, vector, table and union
+    Offset,
+}
+
+impl TypeRef {
+    /// Storage of the value, where fixed-length arrays are stored as their elements
+    pub fn storage(&self) -> Storage {
+        match self {
+            TypeRef::Builtin(Type::String_) => Storage::Offset,
+            TypeRef::Builtin(_) => Storage::Scalar,
+            TypeRef::Vector(_) => Storage::Offset,
+            TypeRef::Array(ty, _) => ty.storage(),
+            TypeRef::Defined(Symbol::Enum(_)) => Storage::Scalar,
+            TypeRef::Defined(Symbol::Struct(_)) => Storage::Struct,
+            TypeRef::Defined(Symbol::Table(_)) | TypeRef::Defined(Symbol::Union(_)) => {
+                Storage::Offset
+            }
+        }
+    }
+}
+
+/// Value of the attribute `name` in metadata, where `Some(None)` means that it has no value
+pub fn attribute<'a>(
+    metadata: &'a [(Identifier, Option<Value>)],
+    name: &str,
+) -> Option<&'a Option<Value>> {
+    metadata
+        .iter()
+        .find(|(id, _)| id == name)
+        .map(|(_, value)| value)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldDef {
+    pub id: Identifier,
+    pub ty: TypeRef,
+    pub storage: Storage,
+    /// Default value normalized for the type, or `None` for non-scalar fields
+    ///
+    /// Members of enums are converted into integers, and scalars without
+    /// explicit defaults take zero as `flatc` does.
+    pub default: Option<Scalar>,
+    pub metadata: Metadata,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+impl FieldDef {
+    /// Optional scalar, i.e. declared with `= null`
+    pub fn is_optional(&self) -> bool {
+        self.default == Some(Scalar::Null)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableDef {
+    /// Fully-qualified name
+    pub name: Identifier,
+    pub namespace: Vec<Identifier>,
+    pub fields: Vec<FieldDef>,
+    pub metadata: Metadata,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+impl TableDef {
+    /// Fields are sorted by size to reduce padding unless `original_order` is specified
+    pub fn sort_by_size(&self) -> bool {
+        attribute(&self.metadata, "original_order").is_none()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructDef {
+    /// Fully-qualified name
+    pub name: Identifier,
+    pub namespace: Vec<Identifier>,
+    pub fields: Vec<FieldDef>,
+    pub layout: StructLayout,
+    pub metadata: Metadata,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnumDef {
+    /// Fully-qualified name
+    pub name: Identifier,
+    pub namespace: Vec<Identifier>,
+    /// Underlying integer type
+    pub ty: Type,
+    pub values: Vec<(Identifier, Integer)>,
+    pub metadata: Metadata,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+/// Member of union, named by the alias or the type name
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnionMember {
+    pub name: Identifier,
+    pub ty: TypeRef,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnionDef {
+    /// Fully-qualified name
+    pub name: Identifier,
+    pub namespace: Vec<Identifier>,
+    pub members: Vec<UnionMember>,
+    pub metadata: Metadata,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MethodDef {
+    pub id: Identifier,
+    pub request: TableId,
+    pub response: TableId,
+    pub metadata: Metadata,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServiceDef {
+    /// Fully-qualified name
+    pub name: Identifier,
+    pub namespace: Vec<Identifier>,
+    pub methods: Vec<MethodDef>,
+    pub doc: Vec<String>,
+    pub span: Span,
+}
+
+/// Handle of a service, i.e. index of [`Schema::services`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ServiceId(pub usize);
+
+/// Top-level declaration listed in [`Schema::declarations`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Declaration {
+    Type(Symbol),
+    Service(ServiceId),
+}
+
+/// Resolved schema, the input of code generators
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schema {
+    pub tables: Vec<TableDef>,
+    pub structs: Vec<StructDef>,
+    pub enums: Vec<EnumDef>,
+    pub unions: Vec<UnionDef>,
+    pub services: Vec<ServiceDef>,
+    /// User-defined attributes declared by `attribute`
+    pub attributes: Vec<String>,
+    pub root_type: Option<TableId>,
+    pub file_identifier: Option<String>,
+    pub file_extension: Option<String>,
+    /// Declarations in the order of the source
+    declarations: Vec<Declaration>,
+    /// Symbol table by fully-qualified names
+    symbols: HashMap<Identifier, Symbol>,
+    services_by_name: HashMap<Identifier, ServiceId>,
+}
+
+/// Levenshtein distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(cur)
+            };
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+impl Schema {
+    /// Build the symbol table, and resolve all references to user-defined types
+    pub fn new(stmt: &[Stmt]) -> Fallible<Self> {
+        let mut schema = Schema::default();
+        for (ns, st) in with_namespace(stmt) {
+            let (id, span, decl) = match st {
+                Stmt::Table(t) => (
+                    &t.id,
+                    &t.span,
+                    Declaration::Type(Symbol::Table(TableId(schema.tables.len()))),
+                ),
+                Stmt::Struct(s) => (
+                    &s.id,
+                    &s.span,
+                    Declaration::Type(Symbol::Struct(StructId(schema.structs.len()))),
+                ),
+                Stmt::Enum(e) => (
+                    &e.id,
+                    &e.span,
+                    Declaration::Type(Symbol::Enum(EnumId(schema.enums.len()))),
+                ),
+                Stmt::Union(u) => (
+                    &u.id,
+                    &u.span,
+                    Declaration::Type(Symbol::Union(UnionId(schema.unions.len()))),
+                ),
+                Stmt::RpcService(s) => (
+                    &s.id,
+                    &s.span,
+                    Declaration::Service(ServiceId(schema.services.len())),
+                ),
+                Stmt::Attribute(name, _) => {
+                    schema.attributes.push(name.clone());
+                    continue;
+                }
+                _ => continue,
+            };
+            let name = qualify(ns, id);
+            if schema.symbols.contains_key(&name) || schema.services_by_name.contains_key(&name) {
+                bail_at!(span, "Duplicated declaration: {}", name);
+            }
+            match decl {
+                Declaration::Type(symbol) => {
+                    schema.symbols.insert(name.clone(), symbol);
+                }
+                Declaration::Service(id) => {
+                    schema.services_by_name.insert(name.clone(), id);
+                }
+            }
+            schema.declarations.push(decl);
+            let namespace = ns.to_vec();
+            let span = span.clone();
+            match st {
+                Stmt::Table(t) => schema.tables.push(TableDef {
+                    name,
+                    namespace,
+                    fields: Vec::new(),
+                    metadata: t.metadata.clone().unwrap_or_default(),
+                    doc: t.doc.clone(),
+                    span,
+                }),
+                Stmt::Struct(s) => schema.structs.push(StructDef {
+                    name,
+                    namespace,
+                    fields: Vec::new(),
+                    layout: StructLayout::default(),
+                    metadata: s.metadata.clone().unwrap_or_default(),
+                    doc: s.doc.clone(),
+                    span,
+                }),
+                Stmt::Enum(e) => {
+                    let (ty, values) = enum_values(e)?;
+                    schema.enums.push(EnumDef {
+                        name,
+                        namespace,
+                        ty,
+                        values,
+                        metadata: e.metadata.clone().unwrap_or_default(),
+                        doc: e.doc.clone(),
+                        span,
+                    })
+                }
+                Stmt::Union(u) => schema.unions.push(UnionDef {
+                    name,
+                    namespace,
+                    members: Vec::new(),
+                    metadata: u.metadata.clone().unwrap_or_default(),
+                    doc: u.doc.clone(),
+                    span,
+                }),
+                Stmt::RpcService(s) => schema.services.push(ServiceDef {
+                    name,
+                    namespace,
+                    methods: Vec::new(),
+                    doc: s.doc.clone(),
+                    span,
+                }),
+                _ => unreachable!(),
+            }
+        }
+
+        for (ns, st) in with_namespace(stmt) {
+            match st {
+                Stmt::Table(t) => {
+                    let fields = schema.fields(ns, &qualify(ns, &t.id), &t.fields)?;
+                    if let Some(Symbol::Table(id)) = schema.lookup(&qualify(ns, &t.id)) {
+                        schema.tables[id.0].fields = fields;
+                        check_field_ids(&schema.tables[id.0])?;
+                    }
+                }
+                Stmt::Struct(s) => {
+                    let name = qualify(ns, &s.id);
+                    let fields = schema.fields(ns, &name, &s.fields)?;
+                    for field in &fields {
+                        schema.check_struct_member(&name, field)?;
+                    }
+                    if let Some(Symbol::Struct(id)) = schema.lookup(&name) {
+                        schema.structs[id.0].fields = fields;
+                    }
+                }
+                Stmt::Union(u) => {
+                    let members = u
+                        .values
+                        .iter()
+                        .map(|val| {
+                            let ty = if val.ty == "string" {
+                                TypeRef::Builtin(Type::String_)
+                            } else {
+                                match schema.resolve_at(ns, &val.ty, &val.span)? {
+                                    symbol @ Symbol::Table(_) | symbol @ Symbol::Struct(_) => {
+                                        TypeRef::Defined(symbol)
+                                    }
+                                    _ => bail_at!(
+                                        &val.span,
+                                        "Member of union {} must be table, struct or string: {}",
+                                        qualify(ns, &u.id),
+                                        val.ty
+                                    ),
+                                }
+                            };
+                            Ok(UnionMember {
+                                name: val.alias.clone().unwrap_or_else(|| val.ty.clone()),
+                                ty,
+                                doc: val.doc.clone(),
+                                span: val.span.clone(),
+                            })
+                        })
+                        .collect::<Fallible<_>>()?;
+                    if let Some(Symbol::Union(id)) = schema.lookup(&qualify(ns, &u.id)) {
+                        schema.unions[id.0].members = members;
+                    }
+                }
+                Stmt::RpcService(s) => {
+                    let methods = s
+                        .methods
+                        .iter()
+                        .map(|method| {
+                            let table = |ty: &str| -> Fallible<TableId> {
+                                match schema.resolve_at(ns, ty, &method.span)? {
+                                    Symbol::Table(id) => Ok(id),
+                                    _ => bail_at!(
+                                        &method.span,
+                                        "Request and response of {}.{} must be table: {}",
+                                        qualify(ns, &s.id),
+                                        method.id,
+                                        ty
+                                    ),
+                                }
+                            };
+                            Ok(MethodDef {
+                                id: method.id.clone(),
+                                request: table(&method.request)?,
+                                response: table(&method.response)?,
+                                metadata: method.metadata.clone().unwrap_or_default(),
+                                doc: method.doc.clone(),
+                                span: method.span.clone(),
+                            })
+                        })
+                        .collect::<Fallible<_>>()?;
+                    if let Some(id) = schema.services_by_name.get(&qualify(ns, &s.id)) {
+                        schema.services[id.0].methods = methods;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let force_align = stmt
+            .iter()
+            .filter_map(|st| match st {
+                Stmt::Struct(s) => Some(force_align(s)),
+                _ => None,
+            })
+            .collect::<Fallible<Vec<_>>>()?;
+        let layouts = struct_layouts(&schema, &force_align)?;
+        for (st, layout) in schema.structs.iter_mut().zip(layouts) {
+            st.layout = layout;
+        }
+
+        schema.root_type = match seek_root_type(stmt)? {
+            Some((ns, id, span)) => match schema.resolve(ns, id) {
+                Some(Symbol::Table(table)) => Some(table),
+                Some(_) => bail_at!(span, "root_type must be table: {}", id),
+                None => bail_at!(span, "Cannot find table: {}", id),
+            },
+            None => None,
+        };
+        schema.file_identifier = seek_file_identifier(stmt)?;
+        schema.file_extension = seek_file_extension(stmt)?;
+        Ok(schema)
+    }
+
+    /// Declarations of types and services in the order of the source
+    pub fn declarations(&self) -> &[Declaration] {
+        &self.declarations
+    }
+
+    /// Table declared by `root_type`
+    pub fn root(&self) -> Option<&TableDef> {
+        self.root_type.map(|id| &self.tables[id.0])
+    }
+
+    /// Look up a service by its fully-qualified name
+    pub fn service(&self, name: &str) -> Option<&ServiceDef> {
+        self.services_by_name
+            .get(name)
+            .map(|id| &self.services[id.0])
+    }
+
+    /// Path of the binary converted from the JSON file, e.g. `monster.json` into `monster.mon`
+    ///
+    /// The extension is `bin` unless `file_extension` is declared, as `flatc` does.
+    pub fn binary_path(&self, json: &Path) -> PathBuf {
+        json.with_extension(self.file_extension.as_deref().unwrap_or("bin"))
+    }
+
+    /// Look up a type by its fully-qualified name
+    pub fn lookup(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).cloned()
+    }
+
+    /// Resolve a type referenced in `namespace`
+    ///
+    /// As `flatc` does, the name is searched in the current namespace, then in
+    /// the enclosing namespaces, and finally in the global scope.
+    pub fn resolve(&self, namespace: &[Identifier], id: &str) -> Option<Symbol> {
+        (0..=namespace.len())
+            .rev()
+            .find_map(|n| self.lookup(&qualify(&namespace[..n], id)))
+    }
+
+    /// Fully-qualified name of the type
+    pub fn name(&self, symbol: Symbol) -> &str {
+        match symbol {
+            Symbol::Table(id) => &self.tables[id.0].name,
+            Symbol::Struct(id) => &self.structs[id.0].name,
+            Symbol::Enum(id) => &self.enums[id.0].name,
+            Symbol::Union(id) => &self.unions[id.0].name,
+        }
+    }
+
+    /// Declared type whose name is closest to the unknown `id`
+    ///
+    /// Names are compared both with and without namespaces,
+    /// and the fully-qualified name is suggested.
+    fn suggest(&self, id: &str) -> Option<&str> {
+        self.symbols
+            .keys()
+            .map(|name| {
+                let short = name.rsplit('.').next().unwrap_or(name);
+                let d = edit_distance(id, name).min(edit_distance(id, short));
+                (d, name.as_str())
+            })
+            .filter(|(d, _)| *d <= id.chars().count().div_ceil(3))
+            .min()
+            .map(|(_, name)| name)
+    }
+
+    /// Resolve a type referenced at `span`, or fail with suggestion
+    fn resolve_at(&self, namespace: &[Identifier], id: &str, span: &Span) -> Fallible<Symbol> {
+        if let Some(symbol) = self.resolve(namespace, id) {
+            return Ok(symbol);
+        }
+        match self.suggest(id) {
+            Some(name) => bail_at!(span, "Unknown type: {}; did you mean `{}`?", id, name),
+            None => bail_at!(span, "Unknown type: {}", id),
+        }
+    }
+
+    /// Check that a member of struct `parent`, or the element of its array, is stored inline,
+    /// i.e. it is a scalar, an enum or a struct
+    fn check_struct_member(&self, parent: &str, field: &FieldDef) -> Fallible<()> {
+        let mut ty = &field.ty;
+        while let TypeRef::Array(elem, _) = ty {
+            ty = elem;
+        }
+        match ty {
+            TypeRef::Defined(symbol) if ty.storage() == Storage::Offset => bail_at!(
+                &field.span,
+                "Member {}.{} must be scalar, enum or struct: {}",
+                parent,
+                field.id,
+                self.name(*symbol)
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    fn resolve_type(&self, namespace: &[Identifier], ty: &Type, span: &Span) -> Fallible<TypeRef> {
+        Ok(match ty {
+            Type::Vector(elem) if matches!(**elem, Type::Vector(_)) => bail_at!(
+                span,
+                "Nested vector is not supported, wrap it in table first: {}",
+                ty
+            ),
+            Type::Vector(ty) => TypeRef::Vector(Box::new(self.resolve_type(namespace, ty, span)?)),
+            Type::Array(ty, len) => {
+                TypeRef::Array(Box::new(self.resolve_type(namespace, ty, span)?), *len)
+            }
+            Type::UserDefined(id) => TypeRef::Defined(self.resolve_at(namespace, id, span)?),
+            ty => TypeRef::Builtin(ty.clone()),
+        })
+    }
+
+    fn fields(
+        &self,
+        namespace: &[Identifier],
+        parent: &str,
+        fields: &[Field],
+    ) -> Fallible<Vec<FieldDef>> {
+        fields
+            .iter()
+            .map(|field| {
+                let ty = self.resolve_type(namespace, &field.ty, &field.span)?;
+                let name = format!("{}.{}", parent, field.id);
+                let default =
+                    self.default_value(&name, &ty, field.default.as_ref(), &field.span)?;
+                Ok(FieldDef {
+                    id: field.id.clone(),
+                    storage: ty.storage(),
+                    ty,
+                    default,
+                    metadata: field.metadata.clone().unwrap_or_default(),
+                    doc: field.doc.clone(),
+                    span: field.span.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Check the default value of field `name` against its type, and normalize it
+    fn default_value(
+        &self,
+        name: &str,
+        ty: &TypeRef,
+        default: Option<&Value>,
+        span: &Span,
+    ) -> Fallible<Option<Scalar>> {
+        let scalar = match ty {
+            TypeRef::Builtin(Type::Bool) => match default {
+                None => Scalar::Bool(false),
+                Some(Value::Scalar(Scalar::Bool(b))) => Scalar::Bool(*b),
+                Some(Value::Scalar(Scalar::Integer(n))) if *n == 0 || *n == 1 => {
+                    Scalar::Bool(*n == 1)
+                }
+                Some(Value::Scalar(Scalar::Null)) => Scalar::Null,
+                Some(value) => bail_at!(span, "Default value of {} must be bool: {}", name, value),
+            },
+            TypeRef::Builtin(Type::Float32) | TypeRef::Builtin(Type::Float64) => match default {
+                None => Scalar::Float(0.0),
+                Some(Value::Scalar(Scalar::Integer(n))) => Scalar::Float(*n as f64),
+                Some(Value::Scalar(Scalar::Float(x))) => Scalar::Float(*x),
+                Some(Value::Scalar(Scalar::Null)) => Scalar::Null,
+                Some(value) => bail_at!(span, "Default value of {} must be float: {}", name, value),
+            },
+            TypeRef::Builtin(ty) if integer_range(ty).is_some() => {
+                let (min, max) = integer_range(ty).unwrap();
+                match default {
+                    None => Scalar::Integer(0),
+                    Some(Value::Scalar(Scalar::Integer(n))) if (min..=max).contains(n) => {
+                        Scalar::Integer(*n)
+                    }
+                    Some(Value::Scalar(Scalar::Integer(n))) => bail_at!(
+                        span,
+                        "Default value of {} is out of range of {}: {}",
+                        name,
+                        ty,
+                        n
+                    ),
+                    Some(Value::Scalar(Scalar::Null)) => Scalar::Null,
+                    Some(value) => {
+                        bail_at!(span, "Default value of {} must be {}: {}", name, ty, value)
+                    }
+                }
+            }
+            TypeRef::Defined(Symbol::Enum(id)) => {
+                self.enum_default(name, &self.enums[id.0], default, span)?
+            }
+            _ => match default {
+                None => return Ok(None),
+                Some(Value::Scalar(Scalar::Null)) => bail_at!(
+                    span,
+                    "Only scalar or enum can be optional with null default: {}",
+                    name
+                ),
+                Some(value) => bail_at!(
+                    span,
+                    "Default value is only allowed for scalar fields: {} = {}",
+                    name,
+                    value
+                ),
+            },
+        };
+        Ok(Some(scalar))
+    }
+
+    /// Convert the default value of enum field into integer
+    ///
+    /// Defaults of `bit_flags` enums may be any combination of members, e.g. `"A B"`.
+    /// Otherwise the default, which is zero if omitted, must be a member as `flatc` requires.
+    fn enum_default(
+        &self,
+        name: &str,
+        e: &EnumDef,
+        default: Option<&Value>,
+        span: &Span,
+    ) -> Fallible<Scalar> {
+        let bit_flags = attribute(&e.metadata, "bit_flags").is_some();
+        let member = |id: &str| -> Fallible<Integer> {
+            match e.values.iter().find(|(val, _)| val == id) {
+                Some((_, value)) => Ok(*value),
+                None => bail_at!(
+                    span,
+                    "Default value of {} is not a member of {}: {}",
+                    name,
+                    e.name,
+                    id
+                ),
+            }
+        };
+        Ok(match default {
+            None => {
+                if !bit_flags && !e.values.iter().any(|(_, value)| *value == 0) {
+                    bail_at!(
+                        span,
+                        "Implicit default value of {} is not a member of {}: 0",
+                        name,
+                        e.name
+                    );
+                }
+                Scalar::Integer(0)
+            }
+            Some(Value::Scalar(Scalar::Null)) => Scalar::Null,
+            Some(Value::Scalar(Scalar::Identifier(id))) => Scalar::Integer(member(id)?),
+            Some(Value::String(ids)) if bit_flags => {
+                let mut value = 0;
+                for id in ids.split_whitespace() {
+                    value |= member(id)?;
+                }
+                Scalar::Integer(value)
+            }
+            Some(Value::String(id)) => Scalar::Integer(member(id)?),
+            Some(Value::Scalar(Scalar::Integer(n))) if bit_flags => {
+                let all = e.values.iter().fold(0, |all, (_, value)| all | value);
+                if n & !all != 0 {
+                    bail_at!(
+                        span,
+                        "Default value of {} is not a combination of {}: {}",
+                        name,
+                        e.name,
+                        n
+                    );
+                }
+                Scalar::Integer(*n)
+            }
+            Some(Value::Scalar(Scalar::Integer(n))) => {
+                if !e.values.iter().any(|(_, value)| value == n) {
+                    bail_at!(
+                        span,
+                        "Default value of {} is not a member of {}: {}",
+                        name,
+                        e.name,
+                        n
+                    );
+                }
+                Scalar::Integer(*n)
+            }
+            Some(value) => bail_at!(
+                span,
+                "Default value of {} must be a member of {}: {}",
+                name,
+                e.name,
+                value
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use combine::{stream::state::State, Parser};
+
+    fn schema(input: &str) -> Fallible<Schema> {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(input, Span::default()))
+            .unwrap();
+        Schema::new(&stmt)
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("Fruit", "Fruit"), 0);
+        assert_eq!(edit_distance("Fruti", "Fruit"), 2);
+        assert_eq!(edit_distance("Frut", "Fruit"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_resolve_fields() {
+        let schema = schema(
+            "namespace A; enum Fruit : byte { Banana } struct Vec3 { x: float; }
+             namespace B; table Monster { pos: A.Vec3; fruit: A.Fruit; name: string;
+                 friends: [Monster]; hp: short; equipped: Equipment; }
+             union Equipment { Monster, A.Vec3, Name: string }",
+        )
+        .unwrap();
+        let monster = match schema.lookup("B.Monster") {
+            Some(Symbol::Table(id)) => &schema.tables[id.0],
+            s => panic!("Unexpected symbol: {:?}", s),
+        };
+        assert_eq!(monster.namespace, ["B"]);
+        let types: Vec<_> = monster
+            .fields
+            .iter()
+            .map(|f| (f.ty.clone(), f.storage))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                (
+                    TypeRef::Defined(Symbol::Struct(StructId(0))),
+                    Storage::Struct
+                ),
+                (TypeRef::Defined(Symbol::Enum(EnumId(0))), Storage::Scalar),
+                (TypeRef::Builtin(Type::String_), Storage::Offset),
+                (
+                    TypeRef::Vector(Box::new(TypeRef::Defined(Symbol::Table(TableId(0))))),
+                    Storage::Offset
+                ),
+                (TypeRef::Builtin(Type::Int16), Storage::Scalar),
+                (TypeRef::Defined(Symbol::Union(UnionId(0))), Storage::Offset),
+            ]
+        );
+        let members: Vec<_> = schema.unions[0]
+            .members
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(members, ["Monster", "A.Vec3", "Name"]);
+        assert_eq!(schema.name(Symbol::Enum(EnumId(0))), "A.Fruit");
+        assert_eq!(schema.enums[0].values, [("Banana".to_string(), 0)]);
+    }
+
+    #[test]
+    fn test_unknown_type() {
+        let err = schema("enum Fruit : byte { Banana } table T { f: Fruti; }").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "1:40: error: Unknown type: Fruti; did you mean `Fruit`?"
+        );
+        let err =
+            schema("namespace A; struct Vec3 { x: float; } table T { v: [A.Vec4]; }").unwrap_err();
+        assert!(err.to_string().ends_with("did you mean `A.Vec3`?"));
+        let err = schema("table T { v: Completely; }").unwrap_err();
+        assert!(err.to_string().ends_with("Unknown type: Completely"));
+        // not visible from other namespaces
+        let err =
+            schema("namespace A; table T { x: int; } namespace B; table U { t: T; }").unwrap_err();
+        assert!(err.to_string().ends_with("did you mean `A.T`?"));
+    }
+
+    #[test]
+    fn test_duplicated_declaration() {
+        let err = schema("namespace A; table T { x: int; } struct T { x: int; }").unwrap_err();
+        assert!(err.to_string().ends_with("Duplicated declaration: A.T"));
+    }
+
+    #[test]
+    fn test_declarations() {
+        let schema = schema(
+            r#"attribute "priority";
+               namespace A;
+               /// Request
+               table Req (original_order) { x: int (priority: 1); }
+               enum E : byte { X }
+               rpc_service S { Get(Req):B.Res (idempotent); }
+               namespace B; table Res { y: int; }
+               root_type Res; file_identifier "RESP";"#,
+        )
+        .unwrap();
+        assert_eq!(
+            schema.declarations(),
+            [
+                Declaration::Type(Symbol::Table(TableId(0))),
+                Declaration::Type(Symbol::Enum(EnumId(0))),
+                Declaration::Service(ServiceId(0)),
+                Declaration::Type(Symbol::Table(TableId(1))),
+            ]
+        );
+        assert_eq!(schema.attributes, ["priority"]);
+        assert_eq!(schema.root().unwrap().name, "B.Res");
+        assert_eq!(schema.file_identifier.as_deref(), Some("RESP"));
+
+        let req = &schema.tables[0];
+        assert_eq!(req.doc, [" Request"]);
+        assert!(!req.sort_by_size());
+        assert_eq!(
+            attribute(&req.fields[0].metadata, "priority"),
+            Some(&Some(Value::Scalar(Scalar::Integer(1))))
+        );
+
+        let service = schema.service("A.S").unwrap();
+        assert_eq!(service.methods[0].request, TableId(0));
+        assert_eq!(service.methods[0].response, TableId(1));
+        assert_eq!(
+            service.methods[0].metadata,
+            [("idempotent".to_string(), None)]
+        );
+        assert!(schema.service("S").is_none());
+    }
+
+    #[test]
+    fn test_default_value() {
+        let resolved = schema(
+            r#"enum Fruit : byte { Banana = -1, Orange = 42 }
+               enum Color : ubyte (bit_flags) { Red, Green, Blue }
+               table T { a: bool = 1; b: float = 2; c: ubyte = 255; d: Fruit = Orange;
+                   e: Color = "Red Blue"; f: Color = Green; g: Fruit = -1; h: int = null;
+                   i: double; j: Color; s: string; }"#,
+        )
+        .unwrap();
+        let defaults: Vec<_> = resolved.tables[0]
+            .fields
+            .iter()
+            .map(|f| f.default.clone())
+            .collect();
+        assert_eq!(
+            defaults,
+            [
+                Some(Scalar::Bool(true)),
+                Some(Scalar::Float(2.0)),
+                Some(Scalar::Integer(255)),
+                Some(Scalar::Integer(42)),
+                Some(Scalar::Integer(5)),
+                Some(Scalar::Integer(2)),
+                Some(Scalar::Integer(-1)),
+                Some(Scalar::Null),
+                Some(Scalar::Float(0.0)),
+                Some(Scalar::Integer(0)),
+                None,
+            ]
+        );
+
+        for (field, message) in &[
+            (
+                "say: string = 5;",
+                "Default value is only allowed for scalar fields: T.say = 5",
+            ),
+            (
+                "meal: Fruit = Apple;",
+                "Default value of T.meal is not a member of Fruit: Apple",
+            ),
+            (
+                "meal: Fruit;",
+                "Implicit default value of T.meal is not a member of Fruit: 0",
+            ),
+            (
+                "meal: Fruit = 3;",
+                "Default value of T.meal is not a member of Fruit: 3",
+            ),
+            (
+                "meal: Fruit = \"Banana Orange\";",
+                "Default value of T.meal is not a member of Fruit: Banana Orange",
+            ),
+            (
+                "c: Color = \"Red Purple\";",
+                "Default value of T.c is not a member of Color: Purple",
+            ),
+            (
+                "c: Color = 8;",
+                "Default value of T.c is not a combination of Color: 8",
+            ),
+            (
+                "x: byte = 128;",
+                "Default value of T.x is out of range of byte: 128",
+            ),
+            (
+                "x: uint = -1;",
+                "Default value of T.x is out of range of uint: -1",
+            ),
+            ("x: int = 1.5;", "Default value of T.x must be int: 1.5"),
+            ("x: int = true;", "Default value of T.x must be int: true"),
+            ("x: bool = 2;", "Default value of T.x must be bool: 2"),
+            (
+                "x: float = false;",
+                "Default value of T.x must be float: false",
+            ),
+            (
+                "x: short = \"1\";",
+                "Default value of T.x must be short: \"1\"",
+            ),
+        ] {
+            let input = format!(
+                "enum Fruit : byte {{ Banana = -1, Orange = 42 }}
+                 enum Color : ubyte (bit_flags) {{ Red, Green, Blue }}
+                 table T {{ {} }}",
+                field
+            );
+            let err = schema(&input).unwrap_err();
+            assert!(err.to_string().ends_with(message), "{}: {}", field, err);
+        }
+    }
+}
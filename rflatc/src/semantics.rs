@@ -1,89 +1,506 @@
 //! Analyze semantics of input statements
 
-use crate::parser::*;
+use crate::{
+    parser::*,
+    schema::{FieldDef, Schema, Symbol, TableDef, TypeRef},
+};
 use failure::*;
-use std::collections::HashMap;
 
-#[derive(Debug)]
-pub struct Entry {
-    pub path: Vec<Identifier>,
-    pub ty: Type,
+/// Value of the attribute `name` in metadata, where `Some(None)` means that it has no value
+fn attribute<'a>(metadata: &'a Option<Metadata>, name: &str) -> Option<&'a Option<Value>> {
+    metadata
+        .as_ref()
+        .and_then(|metadata| crate::schema::attribute(metadata, name))
 }
 
-#[derive(Debug)]
-pub struct Buffer {
-    pub root: Vec<Entry>,
-    pub namespace: Vec<Identifier>,
+/// Kind of user-defined types
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Table,
+    Struct,
+    Enum,
+    Union,
 }
 
-fn seek_namespace(stmt: &[Stmt]) -> Fallible<Vec<Identifier>> {
-    let ns: Vec<_> = stmt
+/// Join namespace and name with dots, e.g. `Game.Monster`
+pub fn qualify(namespace: &[Identifier], id: &str) -> Identifier {
+    namespace
+        .iter()
+        .map(String::as_str)
+        .chain(Some(id))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Pair statements with the namespace in effect, i.e. the one of the last preceding `namespace`
+pub(crate) fn with_namespace(stmt: &[Stmt]) -> impl Iterator<Item = (&[Identifier], &Stmt)> {
+    let mut namespace: &[Identifier] = &[];
+    stmt.iter().map(move |st| {
+        if let Stmt::Namespace(ns, _) = st {
+            namespace = ns;
+        }
+        (namespace, st)
+    })
+}
+
+/// Fully-qualified names of user-defined types in declaration order
+///
+/// Each declaration belongs to the namespace of the last preceding `namespace`
+/// statement, or the root namespace if there is none.
+pub fn qualified_names(stmt: &[Stmt]) -> Vec<(Identifier, Kind)> {
+    with_namespace(stmt)
+        .filter_map(|(ns, st)| match st {
+            Stmt::Table(table) => Some((qualify(ns, &table.id), Kind::Table)),
+            Stmt::Struct(st) => Some((qualify(ns, &st.id), Kind::Struct)),
+            Stmt::Enum(e) => Some((qualify(ns, &e.id), Kind::Enum)),
+            Stmt::Union(union) => Some((qualify(ns, &union.id), Kind::Union)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Seek `root_type` with the namespace where it is declared
+///
+/// `root_type` may be declared at most once.
+pub(crate) fn seek_root_type(
+    stmt: &[Stmt],
+) -> Fallible<Option<(&[Identifier], &Identifier, &Span)>> {
+    let root: Vec<_> = with_namespace(stmt)
+        .filter_map(|(ns, st)| match st {
+            Stmt::Root(id, span) => Some((ns, id, span)),
+            _ => None,
+        })
+        .collect();
+    match root.len() {
+        0 => Ok(None),
+        1 => Ok(Some(root[0])),
+        _ => bail_at!(root[1].2, "Duplicated root_type: {}", root[1].1),
+    }
+}
+
+/// `file_identifier` may be declared at most once
+pub(crate) fn seek_file_identifier(stmt: &[Stmt]) -> Fallible<Option<String>> {
+    let ids: Vec<_> = stmt
         .iter()
         .filter_map(|st| match st {
-            Stmt::Namespace(ns) => Some(ns),
+            Stmt::FileIdentifier(id, span) => Some((id, span)),
             _ => None,
         })
         .collect();
-    match ns.len() {
-        0 => bail!("No namespaces are found"),
-        1 => Ok(ns[0].clone()),
-        _ => bail!("Duplicated namespaces: {:?}", ns),
+    match ids.len() {
+        0 => Ok(None),
+        1 => Ok(Some(ids[0].0.clone())),
+        _ => bail_at!(ids[1].1, "Duplicated file_identifier: {}", ids[1].0),
     }
 }
 
-fn seek_root_type(stmt: &[Stmt]) -> Fallible<Identifier> {
-    let root: Vec<_> = stmt
+/// `file_extension` may be declared at most once, and only with `root_type`
+pub(crate) fn seek_file_extension(stmt: &[Stmt]) -> Fallible<Option<String>> {
+    let ext: Vec<_> = stmt
         .iter()
         .filter_map(|st| match st {
-            Stmt::Root(id) => Some(id),
+            Stmt::FileExtension(ext, span) => Some((ext, span)),
             _ => None,
         })
         .collect();
-    match root.len() {
-        0 => bail!("No root_type are found"),
-        1 => Ok(root[0].clone()),
-        _ => bail!("Duplicated root_type: {:?}", root),
+    match ext.len() {
+        0 => Ok(None),
+        1 => {
+            if !stmt.iter().any(|st| matches!(st, Stmt::Root(..))) {
+                bail_at!(ext[0].1, "file_extension requires root_type");
+            }
+            Ok(Some(ext[0].0.clone()))
+        }
+        _ => bail_at!(ext[1].1, "Duplicated file_extension: {}", ext[1].0),
+    }
+}
+
+fn contains_array(ty: &Type) -> bool {
+    match ty {
+        Type::Array(..) => true,
+        Type::Vector(ty) => contains_array(ty),
+        _ => false,
     }
 }
 
-fn seek_tables(stmt: &[Stmt]) -> HashMap<Identifier, Vec<Entry>> {
-    stmt.iter()
+/// Check that fixed-length arrays are not used in tables
+fn check_tables(stmt: &[Stmt]) -> Fallible<()> {
+    for st in stmt {
+        if let Stmt::Table(table) = st {
+            for field in &table.fields {
+                if contains_array(&field.ty) {
+                    bail_at!(
+                        &field.span,
+                        "Fixed-length array is only allowed in struct: {}.{}",
+                        table.id,
+                        field.id
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that fields with `= null` defaults are not required
+///
+/// Types of them are checked in [`Schema::new`] with the other default values.
+fn check_optional_scalars(stmt: &[Stmt]) -> Fallible<()> {
+    for st in stmt {
+        if let Stmt::Table(table) = st {
+            for field in &table.fields {
+                if field.default != Some(Value::Scalar(Scalar::Null)) {
+                    continue;
+                }
+                if attribute(&field.metadata, "required").is_some() {
+                    bail_at!(
+                        &field.span,
+                        "Optional scalar cannot be required: {}.{}",
+                        table.id,
+                        field.id
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Attributes known to `flatc`
+const BUILTIN_ATTRIBUTES: &[&str] = &[
+    "bit_flags",
+    "cpp_ptr_type",
+    "cpp_ptr_type_get",
+    "cpp_str_flex_ctor",
+    "cpp_str_type",
+    "cpp_type",
+    "csharp_partial",
+    "deprecated",
+    "flexbuffer",
+    "force_align",
+    "hash",
+    "id",
+    "idempotent",
+    "key",
+    "native_custom_alloc",
+    "native_default",
+    "native_inline",
+    "native_type",
+    "native_type_pack_name",
+    "nested_flatbuffer",
+    "original_order",
+    "private",
+    "required",
+    "shared",
+    "streaming",
+];
+
+/// Check that all attributes in metadata are built-in or declared by `attribute`
+fn check_attributes(stmt: &[Stmt]) -> Fallible<()> {
+    let declared: Vec<_> = stmt
+        .iter()
         .filter_map(|st| match st {
-            Stmt::Table(table) => Some((
-                table.id.clone(),
-                table
-                    .fields
-                    .iter()
-                    .map(|e| Entry {
-                        path: vec![e.id.clone()],
-                        ty: e.ty.clone(),
-                    })
-                    .collect(),
-            )),
+            Stmt::Attribute(name, _) => Some(name.as_str()),
             _ => None,
         })
-        .collect()
+        .collect();
+    let check = |metadata: &Option<Metadata>, owner: &str, span: &Span| -> Fallible<()> {
+        for (name, _) in metadata.iter().flatten() {
+            if !BUILTIN_ATTRIBUTES.contains(&name.as_str()) && !declared.contains(&name.as_str()) {
+                bail_at!(span, "Unknown attribute {} in {}", name, owner);
+            }
+        }
+        Ok(())
+    };
+    for st in stmt {
+        match st {
+            Stmt::Table(Table {
+                id,
+                fields,
+                metadata,
+                span,
+                ..
+            })
+            | Stmt::Struct(Struct {
+                id,
+                fields,
+                metadata,
+                span,
+                ..
+            }) => {
+                check(metadata, id, span)?;
+                for field in fields {
+                    check(
+                        &field.metadata,
+                        &format!("{}.{}", id, field.id),
+                        &field.span,
+                    )?;
+                }
+            }
+            Stmt::Enum(Enum {
+                id, metadata, span, ..
+            })
+            | Stmt::Union(Union {
+                id, metadata, span, ..
+            }) => check(metadata, id, span)?,
+            Stmt::RpcService(service) => {
+                for method in &service.methods {
+                    check(
+                        &method.metadata,
+                        &format!("{}.{}", service.id, method.id),
+                        &method.span,
+                    )?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Maximum alignment which can be specified by `force_align`, same as `flatc`
+const MAX_ALIGNMENT: Integer = 32;
+
+/// Alignment specified by `force_align` attribute of the struct
+pub fn force_align(st: &Struct) -> Fallible<Option<usize>> {
+    match attribute(&st.metadata, "force_align") {
+        None => Ok(None),
+        Some(Some(Value::Scalar(Scalar::Integer(align))))
+            if *align > 0 && *align <= MAX_ALIGNMENT && align & (align - 1) == 0 =>
+        {
+            Ok(Some(*align as usize))
+        }
+        Some(_) => bail_at!(
+            &st.span,
+            "force_align of {} must be a power of two integer up to {}",
+            st.id,
+            MAX_ALIGNMENT
+        ),
+    }
 }
 
-impl Buffer {
-    pub fn new(stmt: Vec<Stmt>) -> Fallible<Self> {
-        let root_type = seek_root_type(&stmt)?;
-        let namespace = seek_namespace(&stmt)?;
-        let mut tables = seek_tables(&stmt);
-        let root = tables
-            .remove(&root_type)
-            .ok_or(format_err!("Cannot find table: {}", root_type))?;
+/// Range of values of the integer type, or `None` if it is not an integer
+pub fn integer_range(ty: &Type) -> Option<(Integer, Integer)> {
+    Some(match ty {
+        Type::Int8 => (i8::MIN.into(), i8::MAX.into()),
+        Type::UInt8 => (u8::MIN.into(), u8::MAX.into()),
+        Type::Int16 => (i16::MIN.into(), i16::MAX.into()),
+        Type::UInt16 => (u16::MIN.into(), u16::MAX.into()),
+        Type::Int32 => (i32::MIN.into(), i32::MAX.into()),
+        Type::UInt32 => (u32::MIN.into(), u32::MAX.into()),
+        Type::Int64 => (i64::MIN.into(), i64::MAX.into()),
+        Type::UInt64 => (u64::MIN.into(), u64::MAX.into()),
+        _ => return None,
+    })
+}
 
-        Ok(Buffer { root, namespace })
+/// Underlying type of enum, and values of its members which are bit masks
+/// `1 << position` for `bit_flags` enums
+///
+/// The underlying type must be an integer. Members without explicit values take the previous one plus one, starting from zero.
+/// Values must be strictly ascending, and fit in the underlying integer type.
+/// For `bit_flags` enums, bit positions must be less than its bit width,
+/// and the masks must fit in it, i.e. the sign bit of signed types cannot be used.
+pub fn enum_values(e: &Enum) -> Fallible<(Type, Vec<(Identifier, Integer)>)> {
+    let ty = match &e.ty {
+        Some(ty) => ty,
+        None => bail_at!(
+            &e.span,
+            "Underlying type of enum {} must be specified",
+            e.id
+        ),
+    };
+    let (min, max) = match integer_range(ty) {
+        Some(range) => range,
+        None => bail_at!(
+            &e.span,
+            "Underlying type of enum {} must be integer: {}",
+            e.id,
+            ty
+        ),
+    };
+    let bit_flags = attribute(&e.metadata, "bit_flags").is_some();
+    let bits = (max - min + 1).trailing_zeros() as Integer;
+    let mut prev = None;
+    let mut values = Vec::new();
+    for val in &e.values {
+        let value = match (val.integer_constant, prev) {
+            (Some(value), _) => value,
+            (None, Some(prev)) => prev + 1,
+            (None, None) => 0,
+        };
+        if prev.map(|prev| value <= prev).unwrap_or(false) {
+            bail_at!(
+                &val.span,
+                "Values of enum {} must be strictly ascending: {} = {}",
+                e.id,
+                val.id,
+                value
+            );
+        }
+        prev = Some(value);
+        if bit_flags {
+            if !(0..bits).contains(&value) {
+                bail_at!(
+                    &val.span,
+                    "Bit position of {}.{} is out of range of {}: {}",
+                    e.id,
+                    val.id,
+                    ty,
+                    value
+                );
+            }
+            let mask = 1 << value;
+            if !(min..=max).contains(&mask) {
+                bail_at!(
+                    &val.span,
+                    "Bit flag of {}.{} is out of range of {}: {}",
+                    e.id,
+                    val.id,
+                    ty,
+                    mask
+                );
+            }
+            values.push((val.id.clone(), mask));
+        } else {
+            if !(min..=max).contains(&value) {
+                bail_at!(
+                    &val.span,
+                    "Value of {}.{} is out of range of {}: {}",
+                    e.id,
+                    val.id,
+                    ty,
+                    value
+                );
+            }
+            values.push((val.id.clone(), value));
+        }
     }
+    Ok((ty.clone(), values))
+}
+
+/// Union or vector of unions, which takes two slots in vtable for its types and values
+fn is_union(ty: &TypeRef) -> bool {
+    match ty {
+        TypeRef::Defined(Symbol::Union(_)) => true,
+        TypeRef::Vector(ty) => is_union(ty),
+        _ => false,
+    }
+}
+
+/// Check `id` attributes of table fields, which fix their slots in vtable
+///
+/// Either all or none of the fields have ids. A union field takes `id - 1` for its type,
+/// and the ids must be unique and contiguous from zero, as `flatc` requires.
+pub fn check_field_ids(table: &TableDef) -> Fallible<()> {
+    let has_id = |field: &FieldDef| crate::schema::attribute(&field.metadata, "id").is_some();
+    if !table.fields.iter().any(has_id) {
+        return Ok(());
+    }
+    let mut slots = Vec::new();
+    for field in &table.fields {
+        let id = match crate::schema::attribute(&field.metadata, "id") {
+            Some(Some(Value::Scalar(Scalar::Integer(id)))) if *id >= 0 => *id,
+            Some(_) => bail_at!(
+                &field.span,
+                "id of {}.{} must be non-negative integer",
+                table.name,
+                field.id
+            ),
+            None => bail_at!(
+                &field.span,
+                "Either all or none of fields of {} must have ids: {}",
+                table.name,
+                field.id
+            ),
+        };
+        if is_union(&field.ty) {
+            if id == 0 {
+                bail_at!(
+                    &field.span,
+                    "id of union {}.{} must be at least 1, leaving id - 1 for its type",
+                    table.name,
+                    field.id
+                );
+            }
+            slots.push((id - 1, field));
+        }
+        slots.push((id, field));
+    }
+    slots.sort_by_key(|(id, _)| *id);
+    for (expected, (id, field)) in (0..).zip(slots) {
+        if id < expected {
+            bail_at!(
+                &field.span,
+                "Duplicated id of {}.{}: {}",
+                table.name,
+                field.id,
+                id
+            );
+        }
+        if id > expected {
+            bail_at!(
+                &table.span,
+                "ids of fields of {} must be contiguous from 0, but {} is missing",
+                table.name,
+                expected
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Check that built-in attributes of declarations are used where they take effect
+///
+/// - `bit_flags` for enums
+/// - `force_align` for structs
+/// - `original_order` for tables
+fn check_type_attributes(stmt: &[Stmt]) -> Fallible<()> {
+    for st in stmt {
+        let (id, metadata, span, kind) = match st {
+            Stmt::Table(t) => (&t.id, &t.metadata, &t.span, "table"),
+            Stmt::Struct(s) => (&s.id, &s.metadata, &s.span, "struct"),
+            Stmt::Enum(e) => (&e.id, &e.metadata, &e.span, "enum"),
+            Stmt::Union(u) => (&u.id, &u.metadata, &u.span, "union"),
+            _ => continue,
+        };
+        for (name, allowed) in &[
+            ("bit_flags", "enum"),
+            ("force_align", "struct"),
+            ("original_order", "table"),
+        ] {
+            if attribute(metadata, name).is_some() && kind != *allowed {
+                bail_at!(
+                    span,
+                    "{} is only allowed for {}, but used for {}",
+                    name,
+                    allowed,
+                    id
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check the semantics of statements, and build the schema model
+pub fn analyze(stmt: &[Stmt]) -> Fallible<Schema> {
+    check_tables(stmt)?;
+    check_optional_scalars(stmt)?;
+    check_attributes(stmt)?;
+    check_type_attributes(stmt)?;
+    Schema::new(stmt)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{parser::fbs, remove_comment};
-    use combine::Parser;
-    use std::{fs, io::Read};
+    use crate::{
+        parser::fbs,
+        schema::{FieldDef, TypeRef},
+    };
+    use combine::{stream::state::State, Parser};
+    use std::{fs, io::Read, path::Path};
 
     fn read_example_fbs() -> Vec<Stmt> {
         let mut f =
@@ -91,25 +508,503 @@ mod tests {
         let mut input = String::new();
         f.read_to_string(&mut input)
             .expect("Failed to load example.fbs");
-        let input = remove_comment(&input);
         let (stmt, res) = fbs()
-            .parse(input.as_str())
+            .parse(State::with_positioner(
+                input.as_str(),
+                Span::new("example.fbs"),
+            ))
             .expect("Failed to parse example.fbs");
-        assert_eq!(res, "");
+        assert_eq!(res.input, "");
         stmt
     }
 
+    fn schema(input: &str) -> Fallible<Schema> {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(input, Span::default()))
+            .unwrap();
+        analyze(&stmt)
+    }
+
     #[test]
     fn test_namespace() {
-        let stmt = read_example_fbs();
-        let ns = seek_namespace(&stmt).expect("Namespace cannot find");
-        assert_eq!(vec!["Eclectic"], ns);
+        let schema = analyze(&read_example_fbs()).unwrap();
+        let root = schema.root().expect("root_type cannot find");
+        assert_eq!(vec!["Eclectic"], root.namespace);
+    }
+
+    #[test]
+    fn test_multiple_namespaces() {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "table Global { x: int; }
+                 namespace A.B; table T { g: Global; } enum E : byte { X }
+                 namespace C; table Root { t: A.B.T; } root_type Root;",
+                Span::default(),
+            ))
+            .unwrap();
+        assert_eq!(
+            qualified_names(&stmt),
+            [
+                ("Global".to_string(), Kind::Table),
+                ("A.B.T".to_string(), Kind::Table),
+                ("A.B.E".to_string(), Kind::Enum),
+                ("C.Root".to_string(), Kind::Table),
+            ]
+        );
+        let schema = analyze(&stmt).unwrap();
+        assert_eq!(schema.root().unwrap().name, "C.Root");
+        assert_eq!(schema.root().unwrap().namespace, ["C"]);
+
+        // no namespace is the root namespace
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "table T { x: int; } root_type T;",
+                Span::default(),
+            ))
+            .unwrap();
+        let schema = analyze(&stmt).unwrap();
+        assert_eq!(schema.root().unwrap().name, "T");
+        assert!(schema.root().unwrap().namespace.is_empty());
     }
 
     #[test]
     fn test_root_type() {
         let stmt = read_example_fbs();
-        let root_type = seek_root_type(&stmt).expect("root_type cannot find");
-        assert_eq!("FooBar".to_string(), root_type);
+        let (ns, id, span) = seek_root_type(&stmt)
+            .unwrap()
+            .expect("root_type cannot find");
+        assert_eq!(qualify(ns, id), "Eclectic.FooBar");
+        assert_eq!(span.to_string(), "example.fbs:18:1");
+        assert_eq!(
+            analyze(&stmt).unwrap().root().unwrap().name,
+            "Eclectic.FooBar"
+        );
+
+        for (input, message) in &[
+            ("table T {} root_type X;", "Cannot find table: X"),
+            (
+                "struct S { x: int; } root_type S;",
+                "root_type must be table: S",
+            ),
+            (
+                "table A {} table B {} root_type A; root_type B;",
+                "Duplicated root_type: B",
+            ),
+        ] {
+            let err = schema(input).unwrap_err();
+            assert!(err.to_string().ends_with(message), "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let resolved = schema(
+            "namespace Geometry; struct Vec3 { x: float; }
+             namespace Game.Actors; table Monster { pos: Geometry.Vec3; }
+             namespace Game; table Monster { hp: short; } table Item { x: int; }
+             namespace Game.Actors; union Any { Monster, Item }",
+        )
+        .unwrap();
+        let ns = ["Game".to_string(), "Actors".to_string()];
+        let name = |id| {
+            resolved
+                .resolve(&ns, id)
+                .map(|symbol| resolved.name(symbol))
+        };
+        // current namespace first
+        assert_eq!(name("Monster").unwrap(), "Game.Actors.Monster");
+        // then enclosing ones
+        assert_eq!(name("Item").unwrap(), "Game.Item");
+        assert_eq!(name("Game.Monster").unwrap(), "Game.Monster");
+        // then global
+        assert_eq!(name("Geometry.Vec3").unwrap(), "Geometry.Vec3");
+        assert_eq!(name("Vec3"), None);
+
+        let resolved = schema(
+            "namespace Game; table Monster { hp: short; } namespace Other; root_type Game.Monster;",
+        )
+        .unwrap();
+        assert_eq!(resolved.root().unwrap().name, "Game.Monster");
+        assert!(schema(
+            "namespace Geometry; struct Vec3 { x: float; } namespace Game; struct S { v: Vec3; }",
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_struct_members() {
+        assert!(schema("struct A { x: float; } struct B { a: A; y: double; }").is_ok());
+        assert!(schema("table T { x: float; } struct B { t: T; }").is_err());
+        assert!(schema(
+            "struct A { x: float; } struct B { a: [A:2]; } enum E : byte { X } struct C { e: [E:3]; }",
+        )
+        .is_ok());
+        let err = schema("struct S { t: [T:2]; } table T { x: float; }").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "1:12: error: Member S.t must be scalar, enum or struct: T"
+        );
+    }
+
+    #[test]
+    fn test_union_members() {
+        assert!(schema("table A { x: float; } union U { A, Alias: A }").is_ok());
+        // structs and strings are allowed as `flatc` does
+        assert!(schema("struct S { x: float; } union U { S, Name: string }").is_ok());
+        let err = schema("enum E : byte { A } union U { E }").unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("Member of union U must be table, struct or string: E"));
+        assert!(schema("union U { Unknown }").is_err());
+    }
+
+    #[test]
+    fn test_vector_field() {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "namespace A; table T { names: [string]; } root_type T;",
+                Span::default(),
+            ))
+            .unwrap();
+        let resolved = analyze(&stmt).unwrap();
+        let fields = &resolved.root().unwrap().fields;
+        assert_eq!(fields.len(), 1);
+        assert_eq!(
+            fields[0].ty,
+            TypeRef::Vector(Box::new(TypeRef::Builtin(Type::String_)))
+        );
+
+        for (input, message) in &[
+            (
+                "table T { a: [[int]]; }",
+                "Nested vector is not supported, wrap it in table first: [[int]]",
+            ),
+            (
+                "table T { a: [[T]]; }",
+                "Nested vector is not supported, wrap it in table first: [[T]]",
+            ),
+        ] {
+            let err = schema(input).unwrap_err();
+            assert!(err.to_string().ends_with(message), "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn test_check_tables() {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "struct M { m: [float:16]; } table T { m: M; }",
+                Span::default(),
+            ))
+            .unwrap();
+        assert!(check_tables(&stmt).is_ok());
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "table T { m: [float:16]; }",
+                Span::default(),
+            ))
+            .unwrap();
+        assert!(check_tables(&stmt).is_err());
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "table T { m: [[float:16]]; }",
+                Span::default(),
+            ))
+            .unwrap();
+        assert!(check_tables(&stmt).is_err());
+    }
+
+    #[test]
+    fn test_field_metadata() {
+        let schema = analyze(&read_example_fbs()).unwrap();
+        let root = schema.root().unwrap();
+        let density = root.fields.iter().find(|f| f.id == "density").unwrap();
+        assert_eq!(density.metadata, vec![("deprecated".to_string(), None)]);
+    }
+
+    #[test]
+    fn test_check_attributes() {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                r#"attribute "priority"; table T { a: int (id: 0, priority: 1); }"#,
+                Span::default(),
+            ))
+            .unwrap();
+        assert!(check_attributes(&stmt).is_ok());
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "table T { a: int (deprecatd); }",
+                Span::default(),
+            ))
+            .unwrap();
+        assert!(check_attributes(&stmt).is_err());
+    }
+
+    #[test]
+    fn test_type_attributes() {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "table T (original_order) { a: int; } struct S (force_align: 16) { x: float; }
+                 enum E : ubyte (bit_flags) { A, B = 3, C } enum F : int { A = -1, B, C = 5, D }",
+                Span::default(),
+            ))
+            .unwrap();
+        assert!(check_attributes(&stmt).is_ok());
+        assert!(check_type_attributes(&stmt).is_ok());
+        match &stmt[1] {
+            Stmt::Struct(s) => assert_eq!(force_align(s).unwrap(), Some(16)),
+            st => panic!("Unexpected statement: {:?}", st),
+        }
+
+        for input in &[
+            "table T (force_align: 8) { x: float; }",
+            "struct S (original_order) { x: float; }",
+            "table T (bit_flags) { x: float; }",
+        ] {
+            let (stmt, _) = fbs()
+                .parse(State::with_positioner(*input, Span::default()))
+                .unwrap();
+            assert!(check_type_attributes(&stmt).is_err(), "{}", input);
+        }
+        for input in &[
+            "struct S (force_align: 3) { x: float; }",
+            "struct S (force_align: 64) { x: float; }",
+        ] {
+            let (stmt, _) = fbs()
+                .parse(State::with_positioner(*input, Span::default()))
+                .unwrap();
+            match &stmt[0] {
+                Stmt::Struct(s) => assert!(force_align(s).is_err(), "{}", input),
+                st => panic!("Unexpected statement: {:?}", st),
+            }
+            assert!(analyze(&stmt).is_err(), "{}", input);
+        }
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "table T (unknown) { x: float; }",
+                Span::default(),
+            ))
+            .unwrap();
+        assert!(check_attributes(&stmt).is_err());
+    }
+
+    #[test]
+    fn test_enum_values() {
+        let values = |input| {
+            let (stmt, _) = fbs()
+                .parse(State::with_positioner(input, Span::default()))
+                .unwrap();
+            match &stmt[..] {
+                [Stmt::Enum(e)] => {
+                    enum_values(e).map(|(_, v)| v.into_iter().map(|x| x.1).collect())
+                }
+                _ => panic!("Unexpected statements: {:?}", stmt),
+            }
+        };
+        let ok = |input| -> Vec<Integer> { values(input).unwrap() };
+        assert_eq!(ok("enum E : byte { A, B, C }"), [0, 1, 2]);
+        assert_eq!(
+            ok("enum E : byte { A = -128, B, C = 127 }"),
+            [-128, -127, 127]
+        );
+        assert_eq!(
+            ok("enum E : ulong { A = 0xFFFFFFFFFFFFFFFF }"),
+            [u64::MAX.into()]
+        );
+        assert_eq!(ok("enum F : int { A = -1, B, C = 5, D }"), [-1, 0, 5, 6]);
+        assert_eq!(ok("enum E : ubyte (bit_flags) { A, B = 3, C }"), [1, 8, 16]);
+        assert_eq!(ok("enum E : ulong (bit_flags) { A, B = 63 }"), [1, 1 << 63]);
+
+        for (input, message) in &[
+            (
+                "enum E { A }",
+                "Underlying type of enum E must be specified",
+            ),
+            (
+                "enum E : float { A }",
+                "Underlying type of enum E must be integer: float",
+            ),
+            (
+                "enum E : bool { A }",
+                "Underlying type of enum E must be integer: bool",
+            ),
+            (
+                "enum E : byte { A = 1, B = 1 }",
+                "Values of enum E must be strictly ascending: B = 1",
+            ),
+            (
+                "enum E : byte { A = 2, B = 1 }",
+                "Values of enum E must be strictly ascending: B = 1",
+            ),
+            (
+                "enum E : byte { A = 127, B }",
+                "Value of E.B is out of range of byte: 128",
+            ),
+            (
+                "enum E : ubyte { A = -1 }",
+                "Value of E.A is out of range of ubyte: -1",
+            ),
+            (
+                "enum E : ubyte (bit_flags) { A = 8 }",
+                "Bit position of E.A is out of range of ubyte: 8",
+            ),
+            (
+                "enum E : short (bit_flags) { A = 16 }",
+                "Bit position of E.A is out of range of short: 16",
+            ),
+            (
+                "enum E : int8 (bit_flags) { A, B = 7 }",
+                "Bit flag of E.B is out of range of byte: 128",
+            ),
+        ] {
+            let err = values(*input).unwrap_err();
+            assert!(err.to_string().ends_with(message), "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn test_sort_by_size() {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "namespace A; table T { a: int; } root_type T;",
+                Span::default(),
+            ))
+            .unwrap();
+        assert!(analyze(&stmt).unwrap().tables[0].sort_by_size());
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "namespace A; table T (original_order) { a: int; } root_type T;",
+                Span::default(),
+            ))
+            .unwrap();
+        assert!(!analyze(&stmt).unwrap().tables[0].sort_by_size());
+    }
+
+    #[test]
+    fn test_optional_scalars() {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "namespace A; enum E : byte { X } table T { hp: short = null; e: E = null; mana: short = 150; }
+                 root_type T;",
+            Span::default()))
+            .unwrap();
+        let resolved = analyze(&stmt).unwrap();
+        let optional: Vec<_> = resolved.tables[0]
+            .fields
+            .iter()
+            .map(FieldDef::is_optional)
+            .collect();
+        assert_eq!(optional, [true, true, false]);
+
+        for input in &[
+            "table T { name: string = null; }",
+            "struct S { x: int; } table T { s: S = null; }",
+            "table T { hp: short = null (required); }",
+        ] {
+            assert!(schema(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_rpc_methods() {
+        assert!(schema(
+            "table Req { a: int; } table Res { b: int; } rpc_service S { M(Req):Res; }"
+        )
+        .is_ok());
+        let err =
+            schema("struct Req { a: int; } table Res { b: int; } rpc_service S { M(Req):Res; }")
+                .unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("Request and response of S.M must be table: Req"));
+    }
+
+    #[test]
+    fn test_field_ids() {
+        for input in &[
+            "table T { a: int; b: int; }",
+            "table T { a: int (id: 1); b: int (id: 0); }",
+            "table A {} union U { A } table T { a: int (id: 0); u: U (id: 2); }",
+            "table A {} union U { A } table T { u: [U] (id: 1); a: int (id: 2); }",
+        ] {
+            assert!(schema(input).is_ok(), "{}", input);
+        }
+
+        for (input, message) in &[
+            (
+                "table T { a: int (id: 0); b: int; }",
+                "Either all or none of fields of T must have ids: b",
+            ),
+            (
+                "table T { a: int (id: -1); }",
+                "id of T.a must be non-negative integer",
+            ),
+            (
+                "table T { a: int (id: \"0\"); }",
+                "id of T.a must be non-negative integer",
+            ),
+            (
+                "table T { a: int (id: 0); b: int (id: 0); }",
+                "Duplicated id of T.b: 0",
+            ),
+            (
+                "table T { a: int (id: 0); b: int (id: 2); }",
+                "ids of fields of T must be contiguous from 0, but 1 is missing",
+            ),
+            (
+                "table A {} union U { A } table T { u: U (id: 0); }",
+                "id of union T.u must be at least 1, leaving id - 1 for its type",
+            ),
+            (
+                "table A {} union U { A } table T { a: int (id: 0); u: U (id: 1); }",
+                "Duplicated id of T.u: 0",
+            ),
+        ] {
+            let err = schema(input).unwrap_err();
+            assert!(err.to_string().ends_with(message), "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn test_file_extension() {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                r#"namespace A; table T { a: int; } root_type T; file_extension "mon";"#,
+                Span::default(),
+            ))
+            .unwrap();
+        let schema = analyze(&stmt).unwrap();
+        assert_eq!(schema.file_extension.as_deref(), Some("mon"));
+        assert_eq!(
+            schema.binary_path(Path::new("data/t.json")),
+            Path::new("data/t.mon")
+        );
+
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                r#"table T { a: int; } file_extension "mon";"#,
+                Span::default(),
+            ))
+            .unwrap();
+        assert!(seek_file_extension(&stmt).is_err());
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                r#"table T { a: int; } root_type T; file_extension "a"; file_extension "b";"#,
+                Span::default(),
+            ))
+            .unwrap();
+        assert!(seek_file_extension(&stmt).is_err());
+    }
+
+    #[test]
+    fn test_error_span() {
+        let (stmt, _) = fbs()
+            .parse(State::with_positioner(
+                "namespace A;\ntable T {\n  a: int;\n}\nroot_type X;",
+                Span::default(),
+            ))
+            .unwrap();
+        let err = analyze(&stmt).unwrap_err();
+        assert_eq!(err.to_string(), "5:1: error: Cannot find table: X");
     }
 }
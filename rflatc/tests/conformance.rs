@@ -0,0 +1,162 @@
+//! Parse and analyze the schemas of upstream test suites in `tests/schemas`
+
+use rflatc::{
+    loader,
+    parser::Stmt,
+    schema::{Schema, Symbol},
+    semantics::{self, qualified_names, Kind},
+};
+use std::path::{Path, PathBuf};
+
+fn schema(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/schemas")
+        .join(name)
+}
+
+/// Load the schema with includes, and merge them into one
+fn load(name: &str, include_paths: &[PathBuf]) -> Vec<Stmt> {
+    let sources = loader::load(&schema(name), include_paths)
+        .unwrap_or_else(|e| panic!("Failed to load {}: {}", name, e));
+    loader::merge(&sources)
+}
+
+fn analyze(stmt: Vec<Stmt>) -> Schema {
+    semantics::analyze(&stmt).unwrap_or_else(|e| panic!("Failed to analyze: {}", e))
+}
+
+#[test]
+fn monster_test() {
+    let stmt = load("monster_test.fbs", &[schema("include_test")]);
+    let names = qualified_names(&stmt);
+    for (name, kind) in &[
+        ("TableA", Kind::Table),
+        ("MyGame.OtherNameSpace.TableB", Kind::Table),
+        ("MyGame.OtherNameSpace.Unused", Kind::Struct),
+        ("MyGame.InParentNamespace", Kind::Table),
+        ("MyGame.Example2.Monster", Kind::Table),
+        ("MyGame.Example.Color", Kind::Enum),
+        ("MyGame.Example.Any", Kind::Union),
+        ("MyGame.Example.Vec3", Kind::Struct),
+    ] {
+        assert!(
+            names.contains(&(name.to_string(), *kind)),
+            "{} is not found",
+            name
+        );
+    }
+    let schema = analyze(stmt);
+    let root = schema.root().unwrap();
+    assert_eq!(root.name, "MyGame.Example.Monster");
+    assert_eq!(root.namespace, ["MyGame", "Example"]);
+    assert_eq!(schema.file_identifier.as_deref(), Some("MONS"));
+    assert_eq!(schema.file_extension.as_deref(), Some("mon"));
+    // ids 0..=61 except the hidden type fields of unions, i.e. 7, 43 and 45
+    assert_eq!(root.fields.len(), 59);
+    for (name, size, align) in &[
+        ("MyGame.Example.Test", 4, 2),
+        ("MyGame.Example.Vec3", 32, 16),
+        ("MyGame.Example.Ability", 8, 4),
+        ("MyGame.Example.StructOfStructs", 20, 4),
+    ] {
+        let st = match schema.lookup(name) {
+            Some(Symbol::Struct(id)) => &schema.structs[id.0],
+            s => panic!("Unexpected symbol for {}: {:?}", name, s),
+        };
+        assert_eq!(
+            (st.layout.size, st.layout.align),
+            (*size, *align),
+            "{}",
+            name
+        );
+    }
+    let service = schema.service("MyGame.Example.MonsterStorage").unwrap();
+    assert_eq!(service.methods.len(), 4);
+    assert_eq!(
+        schema.name(Symbol::Table(service.methods[0].request)),
+        "MyGame.Example.Monster"
+    );
+}
+
+#[test]
+fn include_test() {
+    let stmt = load("include_test/include_test1.fbs", &[schema("include_test")]);
+    let names: Vec<_> = qualified_names(&stmt).into_iter().map(|x| x.0).collect();
+    assert_eq!(
+        names,
+        [
+            "MyGame.OtherNameSpace.FromInclude",
+            "MyGame.OtherNameSpace.Unused",
+            "MyGame.OtherNameSpace.TableB",
+            "TableA",
+        ]
+    );
+}
+
+#[test]
+fn eclectic() {
+    let schema = analyze(load("eclectic.fbs", &[]));
+    let root = schema.root().unwrap();
+    assert_eq!(root.name, "Eclectic.FooBar");
+    assert_eq!(root.fields.len(), 4);
+}
+
+#[test]
+fn arrays_test() {
+    let schema = analyze(load("arrays_test.fbs", &[]));
+    assert_eq!(schema.root().unwrap().name, "MyGame.Example.ArrayTable");
+    // sizes of `FLATBUFFERS_STRUCT_END` in the code generated by `flatc`
+    let sizes: Vec<_> = schema
+        .structs
+        .iter()
+        .map(|st| (st.name.as_str(), st.layout.size, st.layout.align))
+        .collect();
+    assert_eq!(
+        sizes,
+        [
+            ("MyGame.Example.NestedStruct", 32, 8),
+            ("MyGame.Example.ArrayStruct", 160, 8)
+        ]
+    );
+}
+
+#[test]
+fn optional_scalars() {
+    let schema = analyze(load("optional_scalars.fbs", &[]));
+    let root = schema.root().unwrap();
+    assert_eq!(root.name, "optional_scalars.ScalarStuff");
+    let optional: Vec<_> = root
+        .fields
+        .iter()
+        .filter(|f| f.is_optional())
+        .map(|f| &f.id)
+        .collect();
+    assert_eq!(optional.len(), 12);
+    assert!(optional.iter().all(|path| path.starts_with("maybe_")));
+}
+
+#[test]
+fn union_vector() {
+    let schema = analyze(load("union_vector.fbs", &[]));
+    let root = schema.root().unwrap();
+    assert_eq!(root.name, "Movie");
+    assert!(root.namespace.is_empty());
+}
+
+#[test]
+fn edge_cases() {
+    let (stmt, errors) = loader::parse_str(
+        r#"
+        table Empty {}
+        enum Race : byte { None = -1, Human, }
+        enum Color : ubyte (bit_flags) { Red, Green, Blue, }
+        union Any { Empty, }
+        table Root { e: Empty; c: Color = Green; any: Any; }
+        root_type Root;
+        "#,
+        Path::new("edge_cases.fbs"),
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+    let schema = analyze(stmt);
+    assert_eq!(schema.root().unwrap().fields.len(), 3);
+}